@@ -1,95 +1,489 @@
-
 extern crate objparser;
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+use objparser::obj::error::ObjError;
+#[cfg(not(feature = "wasm"))]
+use objparser::obj::error::ObjErrorLocation;
+use objparser::obj::mesh::{ObjIndexBuffer, ObjIndexFormat, ObjMesh};
+use objparser::obj::obj::{ObjParseFeatures, ObjParseResult};
+use std::cell::RefCell;
+
 #[repr(C)]
 pub struct ObjParserHandle
 {
-    result: objparser::obj::obj::ObjParseResult
+    result: ObjParseResult,
+    // one mesh per object
+    meshes: Vec<ObjMesh>,
+    // all objects merged into a single mesh
+    combined_mesh: ObjMesh
 }
 
-#[no_mangle]
-#[cfg(not(feature = "wasm"))]
-pub unsafe extern "C" fn parse_obj_from_file_path(file_path_utf8_bytes: *const u8, file_path_byte_count: u32) -> *const ObjParserHandle
+impl ObjParserHandle
 {
-    match std::panic::catch_unwind(|| parse_obj_from_file_path_internal(file_path_utf8_bytes, file_path_byte_count))
+    fn new(result: ObjParseResult) -> Self
     {
-        Ok(parse_result) =>
+        let meshes = result.build_meshes(ObjIndexFormat::U32);
+        let combined_mesh = combine_meshes(&meshes);
+
+        Self { result, meshes, combined_mesh }
+    }
+}
+
+#[repr(u32)]
+#[derive(Copy, Clone)]
+pub enum ObjParserErrorKind
+{
+    None = 0,
+    Io = 1,
+    ParseFloat = 2,
+    ParseInt = 3,
+    TooFewValues = 4,
+    MissingArgument = 5,
+    InvalidPath = 6,
+    InvalidFaceVertex = 7,
+    InconsistentFaceType = 8,
+    TooFewFaceVertices = 9,
+    IndexOutOfBounds = 10,
+    MaterialLibrary = 11,
+    Panic = 12,
+    InvalidArgument = 13,
+    UnexpectedStatement = 14
+}
+
+struct LastError
+{
+    kind: ObjParserErrorKind,
+    line: u32,
+    column: u32,
+    message: String
+}
+
+thread_local!
+{
+    // the error of the last parse call on this thread, cleared when parsing succeeds
+    static LAST_ERROR: RefCell<Option<LastError>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: Option<LastError>)
+{
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = error);
+}
+
+fn to_last_error(error: &ObjError) -> LastError
+{
+    let kind = match error
+    {
+        ObjError::Io { .. } => ObjParserErrorKind::Io,
+        ObjError::ParseFloat(_) => ObjParserErrorKind::ParseFloat,
+        ObjError::ParseInt(_) => ObjParserErrorKind::ParseInt,
+        ObjError::TooFewValues { .. } => ObjParserErrorKind::TooFewValues,
+        ObjError::MissingArgument(_) => ObjParserErrorKind::MissingArgument,
+        ObjError::InvalidPath(_) => ObjParserErrorKind::InvalidPath,
+        ObjError::InvalidFaceVertex(_) => ObjParserErrorKind::InvalidFaceVertex,
+        ObjError::InconsistentFaceType(_) => ObjParserErrorKind::InconsistentFaceType,
+        ObjError::TooFewFaceVertices { .. } => ObjParserErrorKind::TooFewFaceVertices,
+        ObjError::IndexOutOfBounds { .. } => ObjParserErrorKind::IndexOutOfBounds,
+        ObjError::MaterialLibrary { .. } => ObjParserErrorKind::MaterialLibrary,
+        ObjError::InvalidArgument(_) => ObjParserErrorKind::InvalidArgument,
+        ObjError::UnexpectedStatement(_) => ObjParserErrorKind::UnexpectedStatement
+    };
+
+    LastError
+    {
+        kind,
+        line: error.line() as u32,
+        column: error.column() as u32,
+        message: error.to_string()
+    }
+}
+
+// stores the error of the parse result, and returns the handle
+fn finish_parse(parse_result: std::thread::Result<Result<ObjParserHandle, ObjError>>) -> *const ObjParserHandle
+{
+    match parse_result
+    {
+        Ok(Ok(handle)) =>
+        {
+            set_last_error(None);
+            Box::into_raw(Box::new(handle))
+        },
+        Ok(Err(error)) =>
         {
-            match parse_result
-            {
-                Ok(handle) => Box::into_raw(Box::new(handle)),
-                Err(_) => std::ptr::null()
-            }
+            set_last_error(Some(to_last_error(&error)));
+            std::ptr::null()
         },
-        Err(_) => std::ptr::null() // panic occured
+        Err(_) =>
+        {
+            // panic occured
+            set_last_error(Some(LastError { kind: ObjParserErrorKind::Panic, line: 0, column: 0, message: "Panic occured while parsing".into() }));
+            std::ptr::null()
+        }
     }
 }
 
-#[no_mangle]
-#[cfg(not(feature = "wasm"))]
-pub unsafe extern "C" fn parse_obj(file_content_bytes: *const u8, file_content_byte_count: u32) -> *const ObjParserHandle
+fn get_last_error_kind_internal() -> u32
 {
-    let bytes = std::slice::from_raw_parts(file_content_bytes, file_content_byte_count as usize);
-    match std::panic::catch_unwind(|| parse_obj_from_bytes_internal(bytes))
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(ObjParserErrorKind::None, |error| error.kind) as u32)
+}
+
+fn get_last_error_line_internal() -> u32
+{
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(0, |error| error.line))
+}
+
+fn get_last_error_column_internal() -> u32
+{
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(0, |error| error.column))
+}
+
+fn get_last_error_message_length_internal() -> u32
+{
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(0, |error| error.message.len() as u32))
+}
+
+// the pointer is valid until the next parse call on the same thread
+fn get_last_error_message_internal() -> *const u8
+{
+    LAST_ERROR.with(|last_error| last_error.borrow().as_ref().map_or(std::ptr::null(), |error| error.message.as_ptr()))
+}
+
+fn combine_meshes(meshes: &[ObjMesh]) -> ObjMesh
+{
+    let mut positions = vec![];
+    let mut texcoords = meshes.first().and_then(|mesh| mesh.texcoords.as_ref()).map(|_| vec![]);
+    let mut normals = meshes.first().and_then(|mesh| mesh.normals.as_ref()).map(|_| vec![]);
+    let mut colors = meshes.first().and_then(|mesh| mesh.colors.as_ref()).map(|_| vec![]);
+    let mut indices = vec![];
+
+    for mesh in meshes.iter()
     {
-        Ok(parse_result) =>
+        let index_offset = positions.len() as u32;
+        indices.extend(get_indices_internal(mesh).iter().map(|idx| idx + index_offset));
+
+        positions.extend_from_slice(&mesh.positions);
+        if let (Some(texcoords), Some(mesh_texcoords)) = (&mut texcoords, &mesh.texcoords)
         {
-            match parse_result
-            {
-                Ok(handle) => Box::into_raw(Box::new(handle)),
-                Err(_) => std::ptr::null()
-            }
-        },
-        Err(_) => std::ptr::null() // panic occured
+            texcoords.extend_from_slice(mesh_texcoords);
+        }
+
+        if let (Some(normals), Some(mesh_normals)) = (&mut normals, &mesh.normals)
+        {
+            normals.extend_from_slice(mesh_normals);
+        }
+
+        if let (Some(colors), Some(mesh_colors)) = (&mut colors, &mesh.colors)
+        {
+            colors.extend_from_slice(mesh_colors);
+        }
+    }
+
+    ObjMesh
+    {
+        name: vec![],
+        positions,
+        texcoords,
+        normals,
+        colors,
+        tangents: None,
+        indices: ObjIndexBuffer::U32(indices),
+        submeshes: vec![]
     }
 }
 
-#[no_mangle]
-#[cfg(not(feature = "wasm"))]
-pub unsafe extern "C" fn get_vertex_count(handle: *const ObjParserHandle) -> u32
+fn get_indices_internal(mesh: &ObjMesh) -> &[u32]
+{
+    match &mesh.indices
+    {
+        ObjIndexBuffer::U32(indices) => indices.as_slice(),
+        ObjIndexBuffer::U16(_) => &[] // the meshes are always built with u32 indices
+    }
+}
+
+// object_index is None for the combined mesh
+unsafe fn get_mesh<'a>(handle: *const ObjParserHandle, object_index: Option<u32>) -> Option<&'a ObjMesh>
+{
+    let handle = handle.as_ref()?;
+    match object_index
+    {
+        Some(idx) => handle.meshes.get(idx as usize),
+        None => Some(&handle.combined_mesh)
+    }
+}
+
+unsafe fn get_object_count_internal(handle: *const ObjParserHandle) -> u32
 {
     match handle.as_ref()
     {
         None => 0,
-        Some(handle) => handle.result.positions.len() as u32 / 3
+        Some(handle) => handle.result.objects.len() as u32
     }
 }
 
-#[no_mangle]
-#[cfg(not(feature = "wasm"))]
-pub unsafe extern "C" fn get_vertex_positions(handle: *const ObjParserHandle) -> *const f32
+unsafe fn get_object_name_length_internal(handle: *const ObjParserHandle, object_index: u32) -> u32
 {
-    match handle.as_ref()
+    match handle.as_ref().and_then(|handle| handle.result.objects.get(object_index as usize))
+    {
+        None => 0,
+        Some(object) => object.name.len() as u32
+    }
+}
+
+unsafe fn get_object_name_internal(handle: *const ObjParserHandle, object_index: u32) -> *const u8
+{
+    match handle.as_ref().and_then(|handle| handle.result.objects.get(object_index as usize))
     {
         None => std::ptr::null(),
-        Some(handle) => handle.result.positions.as_ptr()
+        Some(object) => object.name.as_ptr()
     }
 }
 
-#[no_mangle]
-#[cfg(not(feature = "wasm"))]
-pub unsafe extern "C" fn get_index_count(handle: *const ObjParserHandle) -> u32
+unsafe fn get_vertex_count_internal(handle: *const ObjParserHandle, object_index: Option<u32>) -> u32
 {
-    match handle.as_ref()
+    match get_mesh(handle, object_index)
     {
         None => 0,
-        Some(handle) => handle.result.indices.len() as u32
+        Some(mesh) => mesh.positions.len() as u32
     }
 }
 
-#[no_mangle]
-#[cfg(not(feature = "wasm"))]
-pub unsafe extern "C" fn get_indices(handle: *const ObjParserHandle) -> *const u32
+unsafe fn get_vertex_positions_internal(handle: *const ObjParserHandle, object_index: Option<u32>) -> *const f32
 {
-    match handle.as_ref()
+    match get_mesh(handle, object_index)
     {
         None => std::ptr::null(),
-        Some(handle) => handle.result.indices.as_ptr()
+        Some(mesh) => mesh.positions.as_ptr() as *const f32
     }
 }
 
+unsafe fn get_vertex_texcoords_internal(handle: *const ObjParserHandle, object_index: Option<u32>) -> *const f32
+{
+    match get_mesh(handle, object_index).and_then(|mesh| mesh.texcoords.as_ref())
+    {
+        None => std::ptr::null(),
+        Some(texcoords) => texcoords.as_ptr() as *const f32
+    }
+}
+
+unsafe fn get_vertex_normals_internal(handle: *const ObjParserHandle, object_index: Option<u32>) -> *const f32
+{
+    match get_mesh(handle, object_index).and_then(|mesh| mesh.normals.as_ref())
+    {
+        None => std::ptr::null(),
+        Some(normals) => normals.as_ptr() as *const f32
+    }
+}
+
+unsafe fn get_index_count_internal(handle: *const ObjParserHandle, object_index: Option<u32>) -> u32
+{
+    match get_mesh(handle, object_index)
+    {
+        None => 0,
+        Some(mesh) => get_indices_internal(mesh).len() as u32
+    }
+}
+
+unsafe fn get_indices_ptr_internal(handle: *const ObjParserHandle, object_index: Option<u32>) -> *const u32
+{
+    match get_mesh(handle, object_index)
+    {
+        None => std::ptr::null(),
+        Some(mesh) => get_indices_internal(mesh).as_ptr()
+    }
+}
+
+/// # Safety
+/// `file_path_utf8_bytes` must point to `file_path_byte_count` readable bytes.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn parse_obj_from_file_path(file_path_utf8_bytes: *const u8, file_path_byte_count: u32, parse_features: u32) -> *const ObjParserHandle
+{
+    finish_parse(std::panic::catch_unwind(|| parse_obj_from_file_path_internal(file_path_utf8_bytes, file_path_byte_count, parse_features)))
+}
+
+/// # Safety
+/// `file_content_bytes` must point to `file_content_byte_count` readable bytes.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn parse_obj(file_content_bytes: *const u8, file_content_byte_count: u32, parse_features: u32) -> *const ObjParserHandle
+{
+    let bytes = std::slice::from_raw_parts(file_content_bytes, file_content_byte_count as usize);
+    finish_parse(std::panic::catch_unwind(|| parse_obj_from_bytes_internal(bytes, parse_features)))
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_vertex_count(handle: *const ObjParserHandle) -> u32
+{
+    get_vertex_count_internal(handle, None)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_vertex_positions(handle: *const ObjParserHandle) -> *const f32
+{
+    get_vertex_positions_internal(handle, None)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_vertex_texcoords(handle: *const ObjParserHandle) -> *const f32
+{
+    get_vertex_texcoords_internal(handle, None)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_vertex_normals(handle: *const ObjParserHandle) -> *const f32
+{
+    get_vertex_normals_internal(handle, None)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_index_count(handle: *const ObjParserHandle) -> u32
+{
+    get_index_count_internal(handle, None)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_indices(handle: *const ObjParserHandle) -> *const u32
+{
+    get_indices_ptr_internal(handle, None)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_count(handle: *const ObjParserHandle) -> u32
+{
+    get_object_count_internal(handle)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_name_length(handle: *const ObjParserHandle, object_index: u32) -> u32
+{
+    get_object_name_length_internal(handle, object_index)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_name(handle: *const ObjParserHandle, object_index: u32) -> *const u8
+{
+    get_object_name_internal(handle, object_index)
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_vertex_count(handle: *const ObjParserHandle, object_index: u32) -> u32
+{
+    get_vertex_count_internal(handle, Some(object_index))
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_vertex_positions(handle: *const ObjParserHandle, object_index: u32) -> *const f32
+{
+    get_vertex_positions_internal(handle, Some(object_index))
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_vertex_texcoords(handle: *const ObjParserHandle, object_index: u32) -> *const f32
+{
+    get_vertex_texcoords_internal(handle, Some(object_index))
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_vertex_normals(handle: *const ObjParserHandle, object_index: u32) -> *const f32
+{
+    get_vertex_normals_internal(handle, Some(object_index))
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_index_count(handle: *const ObjParserHandle, object_index: u32) -> u32
+{
+    get_index_count_internal(handle, Some(object_index))
+}
+
+/// # Safety
+/// `handle` must be null or a handle from a parse function which was not destroyed yet.
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub unsafe extern "C" fn get_object_indices(handle: *const ObjParserHandle, object_index: u32) -> *const u32
+{
+    get_indices_ptr_internal(handle, Some(object_index))
+}
+
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub extern "C" fn get_last_error_kind() -> u32
+{
+    get_last_error_kind_internal()
+}
+
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub extern "C" fn get_last_error_line() -> u32
+{
+    get_last_error_line_internal()
+}
+
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub extern "C" fn get_last_error_column() -> u32
+{
+    get_last_error_column_internal()
+}
+
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub extern "C" fn get_last_error_message_length() -> u32
+{
+    get_last_error_message_length_internal()
+}
+
+#[no_mangle]
+#[cfg(not(feature = "wasm"))]
+pub extern "C" fn get_last_error_message() -> *const u8
+{
+    get_last_error_message_internal()
+}
+
+/// # Safety
+/// `handle` must be a handle from a parse function which was not destroyed yet, it must not be used afterwards.
 #[no_mangle]
 #[cfg(not(feature = "wasm"))]
 pub unsafe extern "C" fn destroy_handle(handle: *mut ObjParserHandle)
@@ -97,27 +491,32 @@ pub unsafe extern "C" fn destroy_handle(handle: *mut ObjParserHandle)
     // After calling this function, the raw pointer is owned by the resulting Box.
     // Specifically, the Box destructor will call the destructor of T and free the allocated memory.
     // https://doc.rust-lang.org/std/boxed/struct.Box.html#method.from_raw
-    Box::from_raw(handle);
+    drop(Box::from_raw(handle));
 }
 
 
 #[cfg(not(feature = "wasm"))]
-unsafe fn parse_obj_from_file_path_internal(file_path_utf8_bytes: *const u8, file_path_byte_count: u32) -> Result<ObjParserHandle, Box<dyn std::error::Error>>
+unsafe fn parse_obj_from_file_path_internal(file_path_utf8_bytes: *const u8, file_path_byte_count: u32, parse_features: u32) -> Result<ObjParserHandle, ObjError>
 {
     let file_path_bytes = std::slice::from_raw_parts(file_path_utf8_bytes, file_path_byte_count as usize);
-    let file_path = std::str::from_utf8(file_path_bytes)?;
+    let file_path = std::str::from_utf8(file_path_bytes).map_err(|_| ObjError::InvalidPath(ObjErrorLocation
+    {
+        line: 0,
+        column: 0,
+        token: String::from_utf8_lossy(file_path_bytes).into_owned()
+    }))?;
 
-    let result = objparser::obj::obj::load_obj(file_path, objparser::obj::obj::ObjParseFeatures::NONE)?;
+    let result = objparser::obj::obj::load_obj(file_path, ObjParseFeatures::from_bits_truncate(parse_features))?;
 
-    Ok(ObjParserHandle { result })
+    Ok(ObjParserHandle::new(result))
 }
 
 #[cfg(not(feature = "wasm"))]
-unsafe fn parse_obj_from_bytes_internal(bytes: &[u8]) -> Result<ObjParserHandle, Box<dyn std::error::Error>>
+fn parse_obj_from_bytes_internal(bytes: &[u8], parse_features: u32) -> Result<ObjParserHandle, ObjError>
 {
-    let result = objparser::obj::obj::load_obj_from_bytes(bytes, objparser::obj::obj::ObjParseFeatures::NONE)?;
+    let result = objparser::obj::obj::load_obj_from_bytes(bytes, ObjParseFeatures::from_bits_truncate(parse_features))?;
 
-    Ok(ObjParserHandle { result })
+    Ok(ObjParserHandle::new(result))
 }
 
 
@@ -127,80 +526,169 @@ unsafe fn parse_obj_from_bytes_internal(bytes: &[u8]) -> Result<ObjParserHandle,
 
 #[wasm_bindgen]
 #[cfg(feature = "wasm")]
-pub fn wasm_parse_obj(file_content_bytes: &[u8]) -> *const ObjParserHandle
+pub fn wasm_parse_obj(file_content_bytes: &[u8], parse_features: u32) -> *const ObjParserHandle
 {
-    match std::panic::catch_unwind(|| wasm_parse_obj_from_bytes_internal(file_content_bytes))
-    {
-        Ok(parse_result) =>
-        {
-            match parse_result
-            {
-                Ok(handle) => Box::into_raw(Box::new(handle)),
-                Err(_) => std::ptr::null()
-            }
-        },
-        Err(_) => std::ptr::null() // panic occured
-    }
+    finish_parse(std::panic::catch_unwind(|| wasm_parse_obj_from_bytes_internal(file_content_bytes, parse_features)))
 }
 
 #[wasm_bindgen]
 #[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn wasm_get_vertex_count(handle: *const ObjParserHandle) -> u32
 {
-    unsafe
-    {
-        match handle.as_ref()
-        {
-            None => 0,
-            Some(handle) => handle.result.positions.len() as u32 / 3
-        }
-    }
+    unsafe { get_vertex_count_internal(handle, None) }
 }
 
 #[wasm_bindgen]
 #[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn wasm_get_vertex_positions(handle: *const ObjParserHandle) -> *const f32
 {
-    unsafe
-    {
-        match handle.as_ref()
-        {
-            None => std::ptr::null(),
-            Some(handle) => handle.result.positions.as_ptr()
-        }
-    }
+    unsafe { get_vertex_positions_internal(handle, None) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_vertex_texcoords(handle: *const ObjParserHandle) -> *const f32
+{
+    unsafe { get_vertex_texcoords_internal(handle, None) }
 }
 
 #[wasm_bindgen]
 #[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_vertex_normals(handle: *const ObjParserHandle) -> *const f32
+{
+    unsafe { get_vertex_normals_internal(handle, None) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn wasm_get_index_count(handle: *const ObjParserHandle) -> u32
 {
-    unsafe
-    {
-        match handle.as_ref()
-        {
-            None => 0,
-            Some(handle) => handle.result.indices.len() as u32
-        }
-    }
+    unsafe { get_index_count_internal(handle, None) }
 }
 
 #[wasm_bindgen]
 #[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn wasm_get_indices(handle: *const ObjParserHandle) -> *const u32
 {
-    unsafe
-    {
-        match handle.as_ref()
-        {
-            None => std::ptr::null(),
-            Some(handle) => handle.result.indices.as_ptr()
-        }
-    }
+    unsafe { get_indices_ptr_internal(handle, None) }
 }
 
 #[wasm_bindgen]
 #[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_count(handle: *const ObjParserHandle) -> u32
+{
+    unsafe { get_object_count_internal(handle) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_name_length(handle: *const ObjParserHandle, object_index: u32) -> u32
+{
+    unsafe { get_object_name_length_internal(handle, object_index) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_name(handle: *const ObjParserHandle, object_index: u32) -> *const u8
+{
+    unsafe { get_object_name_internal(handle, object_index) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_vertex_count(handle: *const ObjParserHandle, object_index: u32) -> u32
+{
+    unsafe { get_vertex_count_internal(handle, Some(object_index)) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_vertex_positions(handle: *const ObjParserHandle, object_index: u32) -> *const f32
+{
+    unsafe { get_vertex_positions_internal(handle, Some(object_index)) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_vertex_texcoords(handle: *const ObjParserHandle, object_index: u32) -> *const f32
+{
+    unsafe { get_vertex_texcoords_internal(handle, Some(object_index)) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_vertex_normals(handle: *const ObjParserHandle, object_index: u32) -> *const f32
+{
+    unsafe { get_vertex_normals_internal(handle, Some(object_index)) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_index_count(handle: *const ObjParserHandle, object_index: u32) -> u32
+{
+    unsafe { get_index_count_internal(handle, Some(object_index)) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub fn wasm_get_object_indices(handle: *const ObjParserHandle, object_index: u32) -> *const u32
+{
+    unsafe { get_indices_ptr_internal(handle, Some(object_index)) }
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+pub fn wasm_get_last_error_kind() -> u32
+{
+    get_last_error_kind_internal()
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+pub fn wasm_get_last_error_line() -> u32
+{
+    get_last_error_line_internal()
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+pub fn wasm_get_last_error_column() -> u32
+{
+    get_last_error_column_internal()
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+pub fn wasm_get_last_error_message_length() -> u32
+{
+    get_last_error_message_length_internal()
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+pub fn wasm_get_last_error_message() -> *const u8
+{
+    get_last_error_message_internal()
+}
+
+#[wasm_bindgen]
+#[cfg(feature = "wasm")]
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
 pub fn wasm_destroy_handle(handle: *mut ObjParserHandle)
 {
     // After calling this function, the raw pointer is owned by the resulting Box.
@@ -208,13 +696,14 @@ pub fn wasm_destroy_handle(handle: *mut ObjParserHandle)
     // https://doc.rust-lang.org/std/boxed/struct.Box.html#method.from_raw
     unsafe
     {
-        Box::from_raw(handle);
+        drop(Box::from_raw(handle));
     }
 }
 
-fn wasm_parse_obj_from_bytes_internal(bytes: &[u8]) -> Result<ObjParserHandle, Box<dyn std::error::Error>>
+#[cfg(feature = "wasm")]
+fn wasm_parse_obj_from_bytes_internal(bytes: &[u8], parse_features: u32) -> Result<ObjParserHandle, ObjError>
 {
-    let result = objparser::obj::obj::load_obj_from_bytes(bytes, objparser::obj::obj::ObjParseFeatures::NONE)?;
+    let result = objparser::obj::obj::load_obj_from_bytes(bytes, ObjParseFeatures::from_bits_truncate(parse_features))?;
 
-    Ok(ObjParserHandle { result })
+    Ok(ObjParserHandle::new(result))
 }
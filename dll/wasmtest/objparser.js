@@ -42,13 +42,14 @@ const objParser = (() =>
 
     /**
      * @param {Uint8Array} file_content_bytes
+     * @param {number} parse_features
      * @returns {number}
      */
-    function wasm_parse_obj(file_content_bytes)
+    function wasm_parse_obj(file_content_bytes, parse_features)
     {
         var ptr0 = passArray8ToWasm0(file_content_bytes, wasm.__wbindgen_malloc);
         var len0 = WASM_VECTOR_LEN;
-        var ret = wasm.wasm_parse_obj(ptr0, len0);
+        var ret = wasm.wasm_parse_obj(ptr0, len0, parse_features);
         return ret;
     }
 
@@ -92,6 +93,169 @@ const objParser = (() =>
         return ret;
     }
 
+    /**
+     * @param {number} handle
+     * @returns {number}
+     */
+    function wasm_get_vertex_texcoords(handle)
+    {
+        var ret = wasm.wasm_get_vertex_texcoords(handle);
+        return ret;
+    }
+
+    /**
+     * @param {number} handle
+     * @returns {number}
+     */
+    function wasm_get_vertex_normals(handle)
+    {
+        var ret = wasm.wasm_get_vertex_normals(handle);
+        return ret;
+    }
+
+    /**
+     * @param {number} handle
+     * @returns {number}
+     */
+    function wasm_get_object_count(handle)
+    {
+        var ret = wasm.wasm_get_object_count(handle);
+        return ret >>> 0;
+    }
+
+    /**
+     * @param {number} handle
+     * @param {number} object_index
+     * @returns {number}
+     */
+    function wasm_get_object_name_length(handle, object_index)
+    {
+        var ret = wasm.wasm_get_object_name_length(handle, object_index);
+        return ret >>> 0;
+    }
+
+    /**
+     * @param {number} handle
+     * @param {number} object_index
+     * @returns {number}
+     */
+    function wasm_get_object_name(handle, object_index)
+    {
+        var ret = wasm.wasm_get_object_name(handle, object_index);
+        return ret;
+    }
+
+    /**
+     * @param {number} handle
+     * @param {number} object_index
+     * @returns {number}
+     */
+    function wasm_get_object_vertex_count(handle, object_index)
+    {
+        var ret = wasm.wasm_get_object_vertex_count(handle, object_index);
+        return ret >>> 0;
+    }
+
+    /**
+     * @param {number} handle
+     * @param {number} object_index
+     * @returns {number}
+     */
+    function wasm_get_object_vertex_positions(handle, object_index)
+    {
+        var ret = wasm.wasm_get_object_vertex_positions(handle, object_index);
+        return ret;
+    }
+
+    /**
+     * @param {number} handle
+     * @param {number} object_index
+     * @returns {number}
+     */
+    function wasm_get_object_vertex_texcoords(handle, object_index)
+    {
+        var ret = wasm.wasm_get_object_vertex_texcoords(handle, object_index);
+        return ret;
+    }
+
+    /**
+     * @param {number} handle
+     * @param {number} object_index
+     * @returns {number}
+     */
+    function wasm_get_object_vertex_normals(handle, object_index)
+    {
+        var ret = wasm.wasm_get_object_vertex_normals(handle, object_index);
+        return ret;
+    }
+
+    /**
+     * @param {number} handle
+     * @param {number} object_index
+     * @returns {number}
+     */
+    function wasm_get_object_index_count(handle, object_index)
+    {
+        var ret = wasm.wasm_get_object_index_count(handle, object_index);
+        return ret >>> 0;
+    }
+
+    /**
+     * @param {number} handle
+     * @param {number} object_index
+     * @returns {number}
+     */
+    function wasm_get_object_indices(handle, object_index)
+    {
+        var ret = wasm.wasm_get_object_indices(handle, object_index);
+        return ret;
+    }
+
+    /**
+     * @returns {number}
+     */
+    function wasm_get_last_error_kind()
+    {
+        var ret = wasm.wasm_get_last_error_kind();
+        return ret >>> 0;
+    }
+
+    /**
+     * @returns {number}
+     */
+    function wasm_get_last_error_line()
+    {
+        var ret = wasm.wasm_get_last_error_line();
+        return ret >>> 0;
+    }
+
+    /**
+     * @returns {number}
+     */
+    function wasm_get_last_error_column()
+    {
+        var ret = wasm.wasm_get_last_error_column();
+        return ret >>> 0;
+    }
+
+    /**
+     * @returns {number}
+     */
+    function wasm_get_last_error_message_length()
+    {
+        var ret = wasm.wasm_get_last_error_message_length();
+        return ret >>> 0;
+    }
+
+    /**
+     * @returns {number}
+     */
+    function wasm_get_last_error_message()
+    {
+        var ret = wasm.wasm_get_last_error_message();
+        return ret;
+    }
+
     /**
      * @param {number} handle
      */
@@ -106,6 +270,22 @@ const objParser = (() =>
         wasm_get_vertex_positions: wasm_get_vertex_positions,
         wasm_get_index_count: wasm_get_index_count,
         wasm_get_indices: wasm_get_indices,
+        wasm_get_vertex_texcoords: wasm_get_vertex_texcoords,
+        wasm_get_vertex_normals: wasm_get_vertex_normals,
+        wasm_get_object_count: wasm_get_object_count,
+        wasm_get_object_name_length: wasm_get_object_name_length,
+        wasm_get_object_name: wasm_get_object_name,
+        wasm_get_object_vertex_count: wasm_get_object_vertex_count,
+        wasm_get_object_vertex_positions: wasm_get_object_vertex_positions,
+        wasm_get_object_vertex_texcoords: wasm_get_object_vertex_texcoords,
+        wasm_get_object_vertex_normals: wasm_get_object_vertex_normals,
+        wasm_get_object_index_count: wasm_get_object_index_count,
+        wasm_get_object_indices: wasm_get_object_indices,
+        wasm_get_last_error_kind: wasm_get_last_error_kind,
+        wasm_get_last_error_line: wasm_get_last_error_line,
+        wasm_get_last_error_column: wasm_get_last_error_column,
+        wasm_get_last_error_message_length: wasm_get_last_error_message_length,
+        wasm_get_last_error_message: wasm_get_last_error_message,
         wasm_destroy_handle: wasm_destroy_handle,
         ensureLoaded: ensureLoaded,
 
@@ -124,12 +304,16 @@ async function ParseObj(bytes)
     await objParser.ensureLoaded();
 
     const start = performance.now();
-    const handle = objParser.wasm_parse_obj(bytes);
+    const handle = objParser.wasm_parse_obj(bytes, 0);
     const end = performance.now();
     console.log(`obj parsed in ${end - start}ms`);
 
     if (handle === 0)
     {
+        const messagePtr = objParser.wasm_get_last_error_message();
+        const messageLength = objParser.wasm_get_last_error_message_length();
+        const message = new TextDecoder().decode(new Uint8Array(objParser.memory(), messagePtr, messageLength));
+        console.error(`failed to parse obj (error kind ${objParser.wasm_get_last_error_kind()}): ${message}`);
         return null;
     }
 
@@ -0,0 +1,203 @@
+// line and column are 1-based, both are 0 if the error is not related to a specific line (e.g. the file cannot be read)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjErrorLocation
+{
+    pub line: usize,
+    pub column: usize,
+    pub token: String
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObjIndexType
+{
+    Position,
+    Texcoord,
+    Normal,
+    // vp
+    ParameterVertex,
+    // curv2, used in trim and hole statements
+    Curve2
+}
+
+#[derive(Debug)]
+pub enum ObjError
+{
+    Io { location: ObjErrorLocation, error: std::io::Error },
+    ParseFloat(ObjErrorLocation),
+    ParseInt(ObjErrorLocation),
+    TooFewValues { location: ObjErrorLocation, expected: usize, found: usize },
+    MissingArgument(ObjErrorLocation),
+    InvalidPath(ObjErrorLocation),
+    InvalidFaceVertex(ObjErrorLocation),
+    InconsistentFaceType(ObjErrorLocation),
+    // also used for line (at least 2) and point (at least 1) elements
+    TooFewFaceVertices { location: ObjErrorLocation, expected: usize, found: usize },
+    IndexOutOfBounds { location: ObjErrorLocation, index_type: ObjIndexType, index: i32, count: usize },
+    // e.g. an unknown curve type in cstype
+    InvalidArgument(ObjErrorLocation),
+    // e.g. parm outside of a curv or surf element
+    UnexpectedStatement(ObjErrorLocation),
+    // an error in a material library, the location is the mtllib statement in the obj file
+    MaterialLibrary { location: ObjErrorLocation, error: Box<ObjError> }
+}
+
+impl ObjError
+{
+    pub fn location(&self) -> &ObjErrorLocation
+    {
+        match self
+        {
+            ObjError::Io { location, .. } |
+            ObjError::ParseFloat(location) |
+            ObjError::ParseInt(location) |
+            ObjError::TooFewValues { location, .. } |
+            ObjError::MissingArgument(location) |
+            ObjError::InvalidPath(location) |
+            ObjError::InvalidFaceVertex(location) |
+            ObjError::InconsistentFaceType(location) |
+            ObjError::TooFewFaceVertices { location, .. } |
+            ObjError::IndexOutOfBounds { location, .. } |
+            ObjError::InvalidArgument(location) |
+            ObjError::UnexpectedStatement(location) |
+            ObjError::MaterialLibrary { location, .. } => location
+        }
+    }
+
+    pub fn line(&self) -> usize
+    {
+        self.location().line
+    }
+
+    pub fn column(&self) -> usize
+    {
+        self.location().column
+    }
+
+    pub fn token(&self) -> &str
+    {
+        &self.location().token
+    }
+
+    pub(super) fn io(path: &std::path::Path, error: std::io::Error) -> Self
+    {
+        ObjError::Io
+        {
+            location: ObjErrorLocation { line: 0, column: 0, token: path.to_string_lossy().into_owned() },
+            error
+        }
+    }
+}
+
+impl std::fmt::Display for ObjError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        let location = self.location();
+        if location.line != 0
+        {
+            write!(f, "Line {}, column {}: ", location.line, location.column)?;
+        }
+
+        match self
+        {
+            ObjError::Io { location, error } => write!(f, "cannot read {}: {}", location.token, error),
+            ObjError::ParseFloat(location) => write!(f, "cannot parse {} as f32", location.token),
+            ObjError::ParseInt(location) => write!(f, "cannot parse {} as i32", location.token),
+            ObjError::TooFewValues { location, expected, found } =>
+                write!(f, "{} values are required for {}, only {} found", expected, location.token, found),
+            ObjError::MissingArgument(location) => write!(f, "{} requires an argument", location.token),
+            ObjError::InvalidPath(location) => write!(f, "invalid path {}", location.token),
+            ObjError::InvalidFaceVertex(location) => write!(f, "invalid face vertex {}", location.token),
+            ObjError::InconsistentFaceType(location) => write!(f, "inconsistent face type at {}", location.token),
+            ObjError::TooFewFaceVertices { expected, found, .. } =>
+                write!(f, "at least {} vertex indices are required, only {} found", expected, found),
+            ObjError::IndexOutOfBounds { index_type, index, count, .. } =>
+                write!(f, "{:?} index {} is out of bounds, {} elements are defined", index_type, index, count),
+            ObjError::InvalidArgument(location) => write!(f, "invalid argument {}", location.token),
+            ObjError::UnexpectedStatement(location) => write!(f, "unexpected {} statement", location.token),
+            ObjError::MaterialLibrary { location, error } => write!(f, "error in material library {}: {}", location.token, error)
+        }
+    }
+}
+
+impl std::error::Error for ObjError
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>
+    {
+        match self
+        {
+            ObjError::Io { error, .. } => Some(error),
+            ObjError::MaterialLibrary { error, .. } => Some(error.as_ref()),
+            _ => None
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObjWarningKind
+{
+    // the statement was ignored, e.g. a face with an invalid index
+    StatementSkipped,
+    // missing or invalid values were replaced with 0, e.g. a vertex with only 2 coordinates
+    StatementRepaired
+}
+
+#[derive(Debug)]
+pub struct ObjWarning
+{
+    pub kind: ObjWarningKind,
+    pub error: ObjError
+}
+
+impl ObjWarning
+{
+    pub fn line(&self) -> usize
+    {
+        self.error.line()
+    }
+
+    pub fn message(&self) -> String
+    {
+        self.error.to_string()
+    }
+}
+
+impl std::fmt::Display for ObjWarning
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self.kind
+        {
+            ObjWarningKind::StatementSkipped => write!(f, "{} (skipped)", self.error),
+            ObjWarningKind::StatementRepaired => write!(f, "{} (repaired)", self.error)
+        }
+    }
+}
+
+// either returns the error, or records it as a warning if error recovery is enabled
+pub(super) struct ErrorRecovery
+{
+    pub enabled: bool,
+    pub warnings: Vec<ObjWarning>
+}
+
+impl ErrorRecovery
+{
+    pub fn new(enabled: bool) -> Self
+    {
+        Self { enabled, warnings: vec![] }
+    }
+
+    pub fn recover(&mut self, error: ObjError, kind: ObjWarningKind) -> Result<(), ObjError>
+    {
+        if self.enabled
+        {
+            self.warnings.push(ObjWarning { kind, error });
+            Ok(())
+        }
+        else
+        {
+            Err(error)
+        }
+    }
+}
@@ -1,24 +1,72 @@
 
 use super::obj::*;
+use super::material::*;
 use std::{fs::File, io::BufWriter};
 use std::io::prelude::*;
+use std::path::{Component, Path, PathBuf};
 
 impl ObjParseResult
 {
+    // the materials are written to a material library with the same name and the extension mtl
+    // obj files can't store a weight and a color for the same vertex, so results with both are not exported
     pub fn export(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>>
     {
+        if let (Some(weights), Some(_)) = (&self.weight_buffer, &self.color_buffer)
+        {
+            if weights.iter().any(|weight| *weight != 1.0)
+            {
+                return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+                    "vertex weights and vertex colors can't be exported together")));
+            }
+        }
+
         let mut writer = BufWriter::new(std::fs::File::create(file_path)?);
 
-        for pos in self.vertex_buffer.iter()
+        if !self.materials.is_empty()
         {
-            writer.write_fmt(format_args!("v {} {} {}\n", pos.x, pos.y, pos.z))?;
+            let library_path = Path::new(file_path).with_extension("mtl");
+            export_mtl(&self.materials, &library_path.to_string_lossy())?;
+
+            writer.write_all(b"mtllib ")?;
+            writer.write_all(library_path.file_name().unwrap_or_default().to_string_lossy().as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+
+        // a vertex can have either a weight or a color, the weight is only written if it's not the default weight,
+        // and then there are no colors
+        for (idx, pos) in self.vertex_buffer.iter().enumerate()
+        {
+            let weight = self.weight_buffer.as_ref().map_or(1.0, |weights| weights[idx]);
+            if weight != 1.0
+            {
+                writer.write_fmt(format_args!("v {} {} {} {}\n", pos.x, pos.y, pos.z, weight))?;
+            }
+            else if let Some(colors) = &self.color_buffer
+            {
+                let color = colors[idx];
+                writer.write_fmt(format_args!("v {} {} {} {} {} {}\n", pos.x, pos.y, pos.z, color.x, color.y, color.z))?;
+            }
+            else
+            {
+                writer.write_fmt(format_args!("v {} {} {}\n", pos.x, pos.y, pos.z))?;
+            }
         }
 
         let has_texcoords = if let Some(texcoords) = &self.texcoord_buffer
         {
-            for uv in texcoords.iter()
+            if let Some(texcoords_w) = &self.texcoord_w_buffer
             {
-                writer.write_fmt(format_args!("vt {} {}\n", uv.x, uv.y))?;
+                for (uv, w) in texcoords.iter().zip(texcoords_w.iter())
+                {
+                    writer.write_fmt(format_args!("vt {} {} {}\n", uv.x, uv.y, w))?;
+                }
+            }
+            else
+            {
+                for uv in texcoords.iter()
+                {
+                    writer.write_fmt(format_args!("vt {} {}\n", uv.x, uv.y))?;
+                }
             }
 
             true
@@ -42,13 +90,16 @@ impl ObjParseResult
             false
         };
 
+        // the material and the smoothing group stay active when the object changes
+        let mut current_material_index = None;
+        let mut current_smoothing_group = None;
         for obj in self.objects.iter()
         {
             if !obj.name.is_empty()
             {
-                writer.write(b"o ")?;
-                writer.write(obj.name.as_slice())?;
-                writer.write(b"\n")?;
+                writer.write_all(b"o ")?;
+                writer.write_all(obj.name.as_slice())?;
+                writer.write_all(b"\n")?;
             }
 
             fn write_positions(writer: &mut BufWriter<File>, px: u32, py: u32, pz: u32) -> Result<(), std::io::Error>
@@ -74,6 +125,7 @@ impl ObjParseResult
                 ))
             }
 
+            #[allow(clippy::too_many_arguments)]
             fn write_all(writer: &mut BufWriter<File>, px: u32, py: u32, pz: u32, tx: u32, ty: u32, tz: u32, nx: u32, ny: u32, nz: u32) -> Result<(), std::io::Error>
             {
                 writer.write_fmt(format_args!("f {}/{}/{} {}/{}/{} {}/{}/{}\n",
@@ -83,8 +135,30 @@ impl ObjParseResult
                 ))
             }
 
-            for tri in obj.indices.iter()
+            let mut submeshes = obj.submeshes.iter().filter(|submesh| submesh.triangle_count > 0).peekable();
+            for (triangle_index, tri) in obj.indices.iter().enumerate()
             {
+                if let Some(submesh) = submeshes.next_if(|submesh| submesh.triangle_start as usize == triangle_index)
+                {
+                    self.write_usemtl(&mut writer, submesh.material_index, &mut current_material_index)?;
+                }
+
+                // objects without smoothing groups are written without s statements, even "s off" would change their normals
+                let smoothing_group = obj.smoothing_group(triangle_index);
+                if !obj.smoothing_groups.is_empty() && Some(smoothing_group) != current_smoothing_group
+                {
+                    if smoothing_group == 0
+                    {
+                        writer.write_all(b"s off\n")?;
+                    }
+                    else
+                    {
+                        writer.write_fmt(format_args!("s {}\n", smoothing_group))?;
+                    }
+
+                    current_smoothing_group = Some(smoothing_group);
+                }
+
                 match (has_texcoords, has_normals)
                 {
                     (false, false) =>
@@ -147,9 +221,377 @@ impl ObjParseResult
                     }
                 };
             }
+
+            for line in obj.lines.iter()
+            {
+                self.write_usemtl(&mut writer, line.material_index, &mut current_material_index)?;
+                writer.write_all(b"l")?;
+                for vertex in obj.line_vertices(line)
+                {
+                    match vertex.texcoord_index
+                    {
+                        Some(tex) if has_texcoords => writer.write_fmt(format_args!(" {}/{}", vertex.position_index + 1, tex + 1))?,
+                        _ => writer.write_fmt(format_args!(" {}", vertex.position_index + 1))?
+                    };
+                }
+
+                writer.write_all(b"\n")?;
+            }
+
+            if !obj.points.is_empty()
+            {
+                writer.write_all(b"p")?;
+                for vertex in obj.points.iter()
+                {
+                    writer.write_fmt(format_args!(" {}", vertex.position_index + 1))?;
+                }
+
+                writer.write_all(b"\n")?;
+            }
         }
 
         writer.flush()?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn write_usemtl(&self, writer: &mut BufWriter<File>, material_index: Option<u32>, current_material_index: &mut Option<u32>)
+        -> Result<(), std::io::Error>
+    {
+        // without a material the previous material stays active, obj files can't switch back to no material
+        if let Some(material_index) = material_index
+        {
+            if *current_material_index != Some(material_index)
+            {
+                writer.write_fmt(format_args!("usemtl {}\n", self.materials[material_index as usize].name))?;
+                *current_material_index = Some(material_index);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// the texture paths are relative to the directory of the material library
+pub fn export_mtl(materials: &[ObjMaterial], file_path: &str) -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut writer = BufWriter::new(std::fs::File::create(file_path)?);
+
+    let directory = Path::new(file_path).parent().filter(|directory| !directory.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    let directory = std::fs::canonicalize(directory)?;
+
+    for (idx, material) in materials.iter().enumerate()
+    {
+        if idx != 0
+        {
+            writer.write_all(b"\n")?;
+        }
+
+        writer.write_fmt(format_args!("newmtl {}\n", material.name))?;
+        write_color(&mut writer, "Ka", &material.ambient_color)?;
+        write_color(&mut writer, "Kd", &material.diffuse_color)?;
+        if material.has_specular_color
+        {
+            write_color(&mut writer, "Ks", &material.specular_color)?;
+        }
+
+        writer.write_fmt(format_args!("Ns {}\n", material.specular_exponent))?;
+        writer.write_fmt(format_args!("d {}\n", material.alpha))?;
+
+        let emissive_color = &material.emissive_color;
+        if emissive_color.r != 0.0 || emissive_color.g != 0.0 || emissive_color.b != 0.0
+        {
+            write_color(&mut writer, "Ke", emissive_color)?;
+        }
+
+        let transmission_filter = &material.transmission_filter;
+        if transmission_filter.r != 1.0 || transmission_filter.g != 1.0 || transmission_filter.b != 1.0
+        {
+            write_color(&mut writer, "Tf", transmission_filter)?;
+        }
+
+        if material.optical_density != 1.0
+        {
+            writer.write_fmt(format_args!("Ni {}\n", material.optical_density))?;
+        }
+
+        if let Some(illumination_model) = material.illumination_model
+        {
+            writer.write_fmt(format_args!("illum {}\n", illumination_model))?;
+        }
+
+        let pbr = &material.pbr;
+        let pbr_values = [
+            ("Pr", pbr.roughness),
+            ("Pm", pbr.metallic),
+            ("Ps", pbr.sheen),
+            ("Pc", pbr.clearcoat_thickness),
+            ("Pcr", pbr.clearcoat_roughness),
+            ("aniso", pbr.anisotropy),
+            ("anisor", pbr.anisotropy_rotation)
+        ];
+
+        for (cmd, value) in pbr_values.iter()
+        {
+            if let Some(value) = value
+            {
+                writer.write_fmt(format_args!("{} {}\n", cmd, value))?;
+            }
+        }
+
+        let textures = [
+            ("map_Ka", &material.ambient_texture),
+            ("map_Kd", &material.diffuse_texture),
+            ("map_Ks", &material.specular_texture),
+            ("map_Ns", &material.specular_exponent_texture),
+            ("map_d", &material.alpha_texture),
+            ("bump", &material.bump_map),
+            ("disp", &material.displacement_map),
+            ("decal", &material.decal_texture),
+            ("map_Pr", &pbr.roughness_texture),
+            ("map_Pm", &pbr.metallic_texture),
+            ("map_Ps", &pbr.sheen_texture),
+            ("norm", &pbr.normal_map)
+        ];
+
+        for (cmd, texture) in textures.iter()
+        {
+            if let Some(texture) = texture
+            {
+                write_texture(&mut writer, cmd, texture, &directory)?;
+            }
+        }
+
+        for texture in material.reflection_maps.iter()
+        {
+            write_texture(&mut writer, "refl", texture, &directory)?;
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_color(writer: &mut BufWriter<File>, cmd: &str, color: &Color) -> Result<(), std::io::Error>
+{
+    writer.write_fmt(format_args!("{} {} {} {}\n", cmd, color.r, color.g, color.b))
+}
+
+fn write_texture(writer: &mut BufWriter<File>, cmd: &str, texture: &ObjTexture, directory: &Path) -> Result<(), std::io::Error>
+{
+    writer.write_all(cmd.as_bytes())?;
+
+    // only the options which are not the default are written
+    let options = &texture.options;
+    let defaults = ObjTextureOptions::default();
+
+    let switches = [
+        ("-blendu", options.blend_u, defaults.blend_u),
+        ("-blendv", options.blend_v, defaults.blend_v),
+        ("-cc", options.color_correction, defaults.color_correction),
+        ("-clamp", options.clamp, defaults.clamp)
+    ];
+
+    for (option, value, default) in switches.iter()
+    {
+        if value != default
+        {
+            writer.write_fmt(format_args!(" {} {}", option, if *value { "on" } else { "off" }))?;
+        }
+    }
+
+    if options.bump_multiplier != defaults.bump_multiplier
+    {
+        writer.write_fmt(format_args!(" -bm {}", options.bump_multiplier))?;
+    }
+
+    if options.boost != defaults.boost
+    {
+        writer.write_fmt(format_args!(" -boost {}", options.boost))?;
+    }
+
+    if options.base != defaults.base || options.gain != defaults.gain
+    {
+        writer.write_fmt(format_args!(" -mm {} {}", options.base, options.gain))?;
+    }
+
+    let vectors = [
+        ("-o", options.offset, defaults.offset),
+        ("-s", options.scale, defaults.scale),
+        ("-t", options.turbulence, defaults.turbulence)
+    ];
+
+    for (option, value, default) in vectors.iter()
+    {
+        if value.x != default.x || value.y != default.y || value.z != default.z
+        {
+            writer.write_fmt(format_args!(" {} {} {} {}", option, value.x, value.y, value.z))?;
+        }
+    }
+
+    if let Some(resolution) = options.resolution
+    {
+        writer.write_fmt(format_args!(" -texres {}", resolution))?;
+    }
+
+    if let Some(channel) = options.channel
+    {
+        let channel = match channel
+        {
+            ObjTextureChannel::Red => "r",
+            ObjTextureChannel::Green => "g",
+            ObjTextureChannel::Blue => "b",
+            ObjTextureChannel::Matte => "m",
+            ObjTextureChannel::Luminance => "l",
+            ObjTextureChannel::Depth => "z"
+        };
+
+        writer.write_fmt(format_args!(" -imfchan {}", channel))?;
+    }
+
+    if let Some(reflection_type) = &options.reflection_type
+    {
+        writer.write_fmt(format_args!(" -type {}", reflection_type))?;
+    }
+
+    // textures which weren't found are expected next to the original library, as the file name is relative to it
+    // paths from other resolvers can't be made relative, the file name is kept as it is in the original library then
+    let path = match texture.path()
+    {
+        Some(path) => Some(path.to_path_buf()),
+        None => std::path::absolute(texture.file.base_directory().join(texture.file.file_name().replace('\\', "/"))).ok()
+    };
+
+    let file_name = path.and_then(|path| relative_path(&path, directory)).map_or_else(|| texture.file_name.clone(), |path|
+    {
+        path.components().map(|component| component.as_os_str().to_string_lossy().into_owned()).collect::<Vec<String>>().join("/")
+    });
+
+    writer.write_fmt(format_args!(" {}\n", file_name))
+}
+
+// the path relative to the directory, None if the paths are not absolute or don't have the same root
+fn relative_path(path: &Path, directory: &Path) -> Option<PathBuf>
+{
+    if !path.is_absolute() || !directory.is_absolute()
+    {
+        return None;
+    }
+
+    let path_components = path.components().collect::<Vec<Component>>();
+    let directory_components = directory.components().collect::<Vec<Component>>();
+
+    let common_count = path_components.iter().zip(directory_components.iter()).take_while(|(a, b)| a == b).count();
+    let has_same_root = path_components.iter().zip(directory_components.iter())
+        .take(common_count)
+        .any(|(component, _)| matches!(component, Component::RootDir));
+
+    if !has_same_root
+    {
+        return None;
+    }
+
+    let mut relative_path = PathBuf::new();
+    for _ in common_count..directory_components.len()
+    {
+        relative_path.push("..");
+    }
+
+    for component in path_components[common_count..].iter()
+    {
+        relative_path.push(component.as_os_str());
+    }
+
+    Some(relative_path)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn round_trip(source: &str, file_name: &str) -> Result<ObjParseResult, Box<dyn std::error::Error>>
+    {
+        let result = load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL)?;
+        let file_path = std::env::temp_dir().join(file_name);
+        let file_path = file_path.to_string_lossy();
+        let _ = std::fs::remove_file(file_path.as_ref());
+        result.export(&file_path)?;
+
+        let exported = load_obj(&file_path, ObjParseFeatures::LOAD_ALL)?;
+        std::fs::remove_file(file_path.as_ref())?;
+        Ok(exported)
+    }
+
+    #[test]
+    fn weights_round_trip()
+    {
+        let result = round_trip("v 0 0 0 0.5\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", "objparser_export_weights.obj").unwrap();
+        assert_eq!(result.weight_buffer, Some(vec![0.5, 1.0, 1.0]));
+        assert!(result.color_buffer.is_none());
+    }
+
+    #[test]
+    fn colors_round_trip()
+    {
+        let result = round_trip("v 0 0 0 1 0 0\nv 1 0 0 0 1 0\nv 0 1 0\nf 1 2 3\n", "objparser_export_colors.obj").unwrap();
+        let colors = result.color_buffer.unwrap().iter().map(|color| [color.x, color.y, color.z]).collect::<Vec<[f32; 3]>>();
+        assert_eq!(colors, vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 1.0]]);
+        assert!(result.weight_buffer.is_none());
+    }
+
+    #[test]
+    fn texcoord_w_round_trip()
+    {
+        let result = round_trip("v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0 0.5\nvt 1 0\nvt 0 1 0.25\nf 1/1 2/2 3/3\n", "objparser_export_texcoord_w.obj").unwrap();
+        assert_eq!(result.texcoord_w_buffer, Some(vec![0.5, 0.0, 0.25]));
+    }
+
+    #[test]
+    fn materials_round_trip()
+    {
+        let directory = std::env::temp_dir().join("objparser_export_materials");
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(directory.join("source/textures")).unwrap();
+        std::fs::create_dir_all(directory.join("exported")).unwrap();
+
+        std::fs::write(directory.join("source/textures/diffuse.png"), b"png").unwrap();
+        std::fs::write(directory.join("source/model.mtl"), "newmtl red\nKd 1 0 0\nmap_Kd -o 0.5 0.25 0 -s 2 2 1 textures/diffuse.png\n\
+            newmtl bumpy\nKd 0 1 0\nKs 0.04 0.04 0.04\nbump -bm 0.5 missing/bump.png\n").unwrap();
+        std::fs::write(directory.join("source/model.obj"), "mtllib model.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n\
+            usemtl red\nf 1 2 3\nusemtl bumpy\nf 2 4 3\n").unwrap();
+
+        let result = load_obj(&directory.join("source/model.obj").to_string_lossy(), ObjParseFeatures::LOAD_ALL).unwrap();
+        let file_path = directory.join("exported/model.obj");
+        result.export(&file_path.to_string_lossy()).unwrap();
+        let exported = load_obj(&file_path.to_string_lossy(), ObjParseFeatures::LOAD_ALL).unwrap();
+
+        let names = exported.objects[0].submeshes.iter()
+            .map(|submesh| exported.materials[submesh.material_index.unwrap() as usize].name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, vec!["red", "bumpy"]);
+        assert!(!exported.materials[0].has_specular_color);
+        assert!(exported.materials[1].has_specular_color);
+
+        let diffuse_texture = exported.materials[0].diffuse_texture.as_ref().unwrap();
+        assert_eq!(diffuse_texture.file_name, "../source/textures/diffuse.png");
+        assert_eq!(diffuse_texture.data().unwrap(), b"png");
+        assert_eq!((diffuse_texture.options.offset.x, diffuse_texture.options.offset.y, diffuse_texture.options.offset.z), (0.5, 0.25, 0.0));
+        assert_eq!((diffuse_texture.options.scale.x, diffuse_texture.options.scale.y, diffuse_texture.options.scale.z), (2.0, 2.0, 1.0));
+
+        // the missing texture still refers to the directory of the original library
+        let bump_map = exported.materials[1].bump_map.as_ref().unwrap();
+        assert_eq!(bump_map.file_name, "../source/missing/bump.png");
+        assert!(bump_map.path().is_none());
+        assert_eq!(bump_map.options.bump_multiplier, 0.5);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn weights_and_colors_are_refused()
+    {
+        let file_name = "objparser_export_weights_and_colors.obj";
+        assert!(round_trip("v 0 0 0 0.5\nv 1 0 0 1 0 0\nv 0 1 0\nf 1 2 3\n", file_name).is_err());
+        assert!(!std::env::temp_dir().join(file_name).exists());
+    }
+}
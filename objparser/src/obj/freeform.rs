@@ -0,0 +1,788 @@
+use super::error::*;
+use super::lines::*;
+use super::obj::*;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObjBasis
+{
+    BasisMatrix,
+    Bezier,
+    BSpline,
+    Cardinal,
+    Taylor
+}
+
+// cstype [rat] type
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ObjFreeFormType
+{
+    pub basis: ObjBasis,
+    pub rational: bool
+}
+
+// curv u0 u1 v1 v2 ...
+pub struct ObjCurve
+{
+    pub object_index: u32,
+    pub material_index: Option<u32>,
+    pub free_form_type: ObjFreeFormType,
+    pub degree: u32,
+    pub start: f32,
+    pub end: f32,
+    // indices into ObjParseResult::vertex_buffer, the weights of rational curves are in ObjParseResult::weight_buffer
+    pub control_points: Vec<u32>,
+    // parm u
+    pub parameters: Vec<f32>
+}
+
+// curv2 vp1 vp2 ..., a curve in the parameter space of a surface
+pub struct ObjCurve2
+{
+    pub free_form_type: ObjFreeFormType,
+    pub degree: u32,
+    // indices into ObjFreeFormGeometry::parameter_vertices
+    pub control_points: Vec<u32>,
+    // parm u
+    pub parameters: Vec<f32>
+}
+
+// a part of a trimming loop, the range of a curv2 between start and end
+#[derive(Copy, Clone)]
+pub struct ObjTrimCurve
+{
+    pub start: f32,
+    pub end: f32,
+    // index into ObjFreeFormGeometry::curves_2d
+    pub curve_index: u32
+}
+
+// surf s0 s1 t0 t1 v1/vt1/vn1 ...
+pub struct ObjSurface
+{
+    pub object_index: u32,
+    pub material_index: Option<u32>,
+    pub free_form_type: ObjFreeFormType,
+    pub degree_u: u32,
+    pub degree_v: u32,
+    pub start_u: f32,
+    pub end_u: f32,
+    pub start_v: f32,
+    pub end_v: f32,
+    // u varies fastest
+    pub control_points: Vec<ObjVertexAbsolute>,
+    // parm u, parm v
+    pub parameters_u: Vec<f32>,
+    pub parameters_v: Vec<f32>,
+    // each trim or hole statement is a closed loop, the surface is only used inside the trim loops (if there are any), and outside of the holes
+    pub trims: Vec<Vec<ObjTrimCurve>>,
+    pub holes: Vec<Vec<ObjTrimCurve>>
+}
+
+#[derive(Default)]
+pub struct ObjFreeFormGeometry
+{
+    // vp u v w, v is 0 and w is 1 if not present
+    pub parameter_vertices: Vec<Vec3>,
+    pub curves: Vec<ObjCurve>,
+    pub curves_2d: Vec<ObjCurve2>,
+    pub surfaces: Vec<ObjSurface>
+}
+
+impl ObjFreeFormGeometry
+{
+    pub fn is_empty(&self) -> bool
+    {
+        self.parameter_vertices.is_empty() && self.curves.is_empty() && self.curves_2d.is_empty() && self.surfaces.is_empty()
+    }
+}
+
+// the number of elements which can be referenced at the current line
+pub(super) struct ObjVertexCounts
+{
+    pub position: usize,
+    pub texcoord: Option<usize>,
+    pub normal: Option<usize>
+}
+
+// the element which parm, trim and hole statements refer to, until the next end statement
+#[derive(Copy, Clone)]
+enum ObjFreeFormElement
+{
+    Curve(usize),
+    Curve2(usize),
+    Surface(usize)
+}
+
+pub(super) struct FreeFormParser
+{
+    pub geometry: ObjFreeFormGeometry,
+    free_form_type: ObjFreeFormType,
+    degree_u: u32,
+    degree_v: u32,
+    current_element: Option<ObjFreeFormElement>,
+    temp_vertices: Vec<ObjVertexRelative>,
+    temp_vertices_absolute: Vec<ObjVertexAbsolute>
+}
+
+fn read_values<'a, Iter>(source_line: SourceLine, cmd: &[u8], params_iter: &mut Iter, values: &mut [f32]) -> Result<(), ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    for idx in 0..values.len()
+    {
+        let segment = params_iter.next()
+            .ok_or_else(|| ObjError::TooFewValues { location: source_line.location(cmd), expected: values.len(), found: idx })?;
+        values[idx] = try_parse_f32(source_line, segment)?;
+    }
+
+    Ok(())
+}
+
+fn read_degree(source_line: SourceLine, segment: &[u8]) -> Result<u32, ObjError>
+{
+    let degree = try_parse_i32(source_line, segment)?;
+    if degree < 0
+    {
+        Err(ObjError::InvalidArgument(source_line.location(segment)))
+    }
+    else
+    {
+        Ok(degree as u32)
+    }
+}
+
+impl FreeFormParser
+{
+    pub fn new() -> Self
+    {
+        Self
+        {
+            geometry: ObjFreeFormGeometry::default(),
+            free_form_type: ObjFreeFormType { basis: ObjBasis::Bezier, rational: false },
+            degree_u: 1,
+            degree_v: 1,
+            current_element: None,
+            temp_vertices: vec![],
+            temp_vertices_absolute: vec![]
+        }
+    }
+
+    pub fn references_object(&self, object_index: u32) -> bool
+    {
+        self.geometry.curves.iter().any(|curve| curve.object_index == object_index) ||
+        self.geometry.surfaces.iter().any(|surface| surface.object_index == object_index)
+    }
+
+    // called when an object before object_index is removed
+    pub fn shift_object_indices(&mut self)
+    {
+        for curve in self.geometry.curves.iter_mut()
+        {
+            curve.object_index -= 1;
+        }
+
+        for surface in self.geometry.surfaces.iter_mut()
+        {
+            surface.object_index -= 1;
+        }
+    }
+
+    pub fn parse_statement<'a, Iter>(&mut self, source_line: SourceLine, cmd: &[u8], params_iter: &mut Iter, counts: &ObjVertexCounts,
+        object_index: u32, material_index: Option<u32>) -> Result<(), ObjError>
+    where
+        Iter: Iterator<Item = &'a [u8]>
+    {
+        match cmd
+        {
+            b"vp" =>
+            {
+                let mut values = [0.0, 0.0, 1.0];
+                read_values(source_line, cmd, params_iter, &mut values[..1])?;
+                for value in values[1..].iter_mut()
+                {
+                    if let Some(segment) = params_iter.next()
+                    {
+                        *value = try_parse_f32(source_line, segment)?;
+                    }
+                }
+
+                self.geometry.parameter_vertices.push(Vec3::new(values[0], values[1], values[2]));
+            },
+            b"cstype" =>
+            {
+                let mut segment = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(cmd)))?;
+                let rational = segment == b"rat";
+                if rational
+                {
+                    segment = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(segment)))?;
+                }
+
+                let basis = match segment
+                {
+                    b"bmatrix" => ObjBasis::BasisMatrix,
+                    b"bezier" => ObjBasis::Bezier,
+                    b"bspline" => ObjBasis::BSpline,
+                    b"cardinal" => ObjBasis::Cardinal,
+                    b"taylor" => ObjBasis::Taylor,
+                    _ => return Err(ObjError::InvalidArgument(source_line.location(segment)))
+                };
+
+                self.free_form_type = ObjFreeFormType { basis, rational };
+            },
+            b"deg" =>
+            {
+                // deg degu [degv]
+                let segment = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(cmd)))?;
+                let degree_u = read_degree(source_line, segment)?;
+                let degree_v = if let Some(segment) = params_iter.next() { read_degree(source_line, segment)? } else { degree_u };
+
+                self.degree_u = degree_u;
+                self.degree_v = degree_v;
+            },
+            b"curv" =>
+            {
+                let mut range = [0f32; 2];
+                read_values(source_line, cmd, params_iter, &mut range)?;
+                read_face(source_line, cmd, params_iter, &mut self.temp_vertices, 2)?;
+                resolve_vertices(source_line, &self.temp_vertices, &mut self.temp_vertices_absolute, counts.position, counts.texcoord, counts.normal)?;
+
+                self.geometry.curves.push(ObjCurve
+                {
+                    object_index,
+                    material_index,
+                    free_form_type: self.free_form_type,
+                    degree: self.degree_u,
+                    start: range[0],
+                    end: range[1],
+                    control_points: self.temp_vertices_absolute.iter().map(|vertex| vertex.position_index).collect(),
+                    parameters: vec![]
+                });
+
+                self.current_element = Some(ObjFreeFormElement::Curve(self.geometry.curves.len() - 1));
+            },
+            b"curv2" =>
+            {
+                read_face(source_line, cmd, params_iter, &mut self.temp_vertices, 2)?;
+                let parameter_vertex_count = self.geometry.parameter_vertices.len();
+                let control_points = self.temp_vertices.iter()
+                    .map(|vertex| map_index(source_line, vertex, vertex.position_index, ObjIndexType::ParameterVertex, Some(parameter_vertex_count)))
+                    .collect::<Result<Vec<u32>, ObjError>>()?;
+
+                self.geometry.curves_2d.push(ObjCurve2
+                {
+                    free_form_type: self.free_form_type,
+                    degree: self.degree_u,
+                    control_points,
+                    parameters: vec![]
+                });
+
+                self.current_element = Some(ObjFreeFormElement::Curve2(self.geometry.curves_2d.len() - 1));
+            },
+            b"surf" =>
+            {
+                let mut range = [0f32; 4];
+                read_values(source_line, cmd, params_iter, &mut range)?;
+                read_face(source_line, cmd, params_iter, &mut self.temp_vertices, 1)?;
+                resolve_vertices(source_line, &self.temp_vertices, &mut self.temp_vertices_absolute, counts.position, counts.texcoord, counts.normal)?;
+
+                self.geometry.surfaces.push(ObjSurface
+                {
+                    object_index,
+                    material_index,
+                    free_form_type: self.free_form_type,
+                    degree_u: self.degree_u,
+                    degree_v: self.degree_v,
+                    start_u: range[0],
+                    end_u: range[1],
+                    start_v: range[2],
+                    end_v: range[3],
+                    control_points: self.temp_vertices_absolute.clone(),
+                    parameters_u: vec![],
+                    parameters_v: vec![],
+                    trims: vec![],
+                    holes: vec![]
+                });
+
+                self.current_element = Some(ObjFreeFormElement::Surface(self.geometry.surfaces.len() - 1));
+            },
+            b"parm" =>
+            {
+                let element = self.current_element.ok_or_else(|| ObjError::UnexpectedStatement(source_line.location(cmd)))?;
+                let direction = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(cmd)))?;
+
+                let parameters = match (element, direction)
+                {
+                    (ObjFreeFormElement::Curve(idx), b"u") => &mut self.geometry.curves[idx].parameters,
+                    (ObjFreeFormElement::Curve2(idx), b"u") => &mut self.geometry.curves_2d[idx].parameters,
+                    (ObjFreeFormElement::Surface(idx), b"u") => &mut self.geometry.surfaces[idx].parameters_u,
+                    (ObjFreeFormElement::Surface(idx), b"v") => &mut self.geometry.surfaces[idx].parameters_v,
+                    _ => return Err(ObjError::InvalidArgument(source_line.location(direction)))
+                };
+
+                let values = params_iter.map(|segment| try_parse_f32(source_line, segment)).collect::<Result<Vec<f32>, ObjError>>()?;
+                parameters.extend_from_slice(&values);
+            },
+            b"trim" | b"hole" =>
+            {
+                let surface_index = match self.current_element
+                {
+                    Some(ObjFreeFormElement::Surface(idx)) => idx,
+                    _ => return Err(ObjError::UnexpectedStatement(source_line.location(cmd)))
+                };
+
+                // u0 u1 curv2d, repeated
+                let mut trim_loop = vec![];
+                let segments = params_iter.collect::<Vec<&[u8]>>();
+                if segments.is_empty()
+                {
+                    return Err(ObjError::MissingArgument(source_line.location(cmd)));
+                }
+
+                // the count has to be a multiple of 3, the incomplete curve at the end is reported
+                let incomplete_start = segments.len() / 3 * 3;
+                if incomplete_start != segments.len()
+                {
+                    return Err(ObjError::InvalidArgument(source_line.location(segments[incomplete_start])));
+                }
+
+                for curve in segments.chunks_exact(3)
+                {
+                    let index = try_parse_i32(source_line, curve[2])?;
+                    let count = self.geometry.curves_2d.len();
+                    let absolute_index = if index <= 0 { count as i64 + index as i64 } else { index as i64 - 1 };
+                    if absolute_index < 0 || absolute_index as usize >= count
+                    {
+                        return Err(ObjError::IndexOutOfBounds { location: source_line.location(curve[2]), index_type: ObjIndexType::Curve2, index, count });
+                    }
+
+                    trim_loop.push(ObjTrimCurve
+                    {
+                        start: try_parse_f32(source_line, curve[0])?,
+                        end: try_parse_f32(source_line, curve[1])?,
+                        curve_index: absolute_index as u32
+                    });
+                }
+
+                let surface = &mut self.geometry.surfaces[surface_index];
+                if cmd == b"trim" { surface.trims.push(trim_loop); } else { surface.holes.push(trim_loop); }
+            },
+            b"end" =>
+            {
+                self.current_element.take().ok_or_else(|| ObjError::UnexpectedStatement(source_line.location(cmd)))?;
+            },
+            _ => { }
+        };
+
+        Ok(())
+    }
+}
+
+// a b-spline basis, bezier curves are converted to b-splines
+struct Basis
+{
+    knots: Vec<f32>,
+    degree: usize,
+    count: usize,
+    // the buffers of evaluate, so the curves and surfaces are evaluated without allocating
+    values: Vec<f32>,
+    left: Vec<f32>,
+    right: Vec<f32>
+}
+
+impl Basis
+{
+    // returns None for unsupported types, or if the number of control points doesn't match the parameters
+    fn new(free_form_type: ObjFreeFormType, degree: u32, parameters: &[f32], count: usize) -> Option<Basis>
+    {
+        let degree = degree as usize;
+        let knots = match free_form_type.basis
+        {
+            ObjBasis::BSpline => parameters.to_vec(),
+            ObjBasis::Bezier =>
+            {
+                // the parameters are the ends of the segments, which share their end points
+                let parameters = if parameters.len() < 2 { &[0.0, 1.0][..] } else { parameters };
+                let mut knots = vec![parameters[0]];
+                for parameter in parameters.iter()
+                {
+                    knots.resize(knots.len() + degree, *parameter);
+                }
+
+                knots.push(parameters[parameters.len() - 1]);
+                knots
+            },
+            _ => return None
+        };
+
+        if count == 0 || knots.len() != count + degree + 1
+        {
+            return None;
+        }
+
+        Some(Basis { knots, degree, count, values: vec![0.0; degree + 1], left: vec![0.0; degree + 1], right: vec![0.0; degree + 1] })
+    }
+
+    // returns the index of the first control point, and the values of the degree + 1 non-zero basis functions
+    fn evaluate(&mut self, t: f32) -> (usize, &[f32])
+    {
+        let knots = &self.knots;
+        let degree = self.degree;
+
+        // the span which contains t, the ends are clamped
+        let last = self.count - 1;
+        let span = if t >= knots[last + 1]
+        {
+            (degree..=last).rev().find(|idx| knots[*idx] < knots[*idx + 1]).unwrap_or(last)
+        }
+        else if t <= knots[degree]
+        {
+            (degree..=last).find(|idx| knots[*idx] < knots[*idx + 1]).unwrap_or(degree)
+        }
+        else
+        {
+            (degree..=last).rev().find(|idx| knots[*idx] <= t).unwrap_or(degree)
+        };
+
+        let (values, left, right) = (&mut self.values, &mut self.left, &mut self.right);
+        values[0] = 1.0;
+
+        for j in 1..=degree
+        {
+            left[j] = t - knots[span + 1 - j];
+            right[j] = knots[span + j] - t;
+            let mut saved = 0.0;
+            for r in 0..j
+            {
+                let denominator = right[r + 1] + left[j - r];
+                let temp = if denominator != 0.0 { values[r] / denominator } else { 0.0 };
+                values[r] = saved + right[r + 1] * temp;
+                saved = left[j - r] * temp;
+            }
+
+            values[j] = saved;
+        }
+
+        (span - degree, values.as_slice())
+    }
+}
+
+// even-odd rule
+fn is_point_in_loop(point: Vec2, trim_loop: &[Vec2]) -> bool
+{
+    let mut inside = false;
+    for i in 0..trim_loop.len()
+    {
+        let a = trim_loop[i];
+        let b = trim_loop[(i + 1) % trim_loop.len()];
+        if (a.y > point.y) != (b.y > point.y) && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+
+    inside
+}
+
+// the maximum number of segments per curve and per surface side, a surface has (segments + 1)^2 vertices
+pub const MAX_TESSELLATION_SEGMENTS: u32 = 4096;
+
+impl ObjParseResult
+{
+    fn weight(&self, position_index: u32) -> f32
+    {
+        self.weight_buffer.as_ref().map_or(1.0, |weights| weights[position_index as usize])
+    }
+
+    // appends a vertex and keeps the other per-vertex buffers the same length as vertex_buffer
+    fn push_tessellated_vertex(&mut self, position: Vec3) -> u32
+    {
+        self.vertex_buffer.push(position);
+        if let Some(weights) = &mut self.weight_buffer
+        {
+            weights.push(1.0);
+        }
+
+        if let Some(colors) = &mut self.color_buffer
+        {
+            colors.push(Vec3::new(1.0, 1.0, 1.0));
+        }
+
+        if let Some(masks) = &mut self.mask_buffer
+        {
+            masks.push(0.0);
+        }
+
+        self.vertex_buffer.len() as u32 - 1
+    }
+
+    fn evaluate_curve(&self, curve: &ObjCurve, basis: &mut Basis, t: f32) -> Vec3
+    {
+        let (first, values) = basis.evaluate(t);
+        let mut point = Vec3::new(0.0, 0.0, 0.0);
+        let mut weight_sum = 0.0;
+        for (idx, value) in values.iter().enumerate()
+        {
+            let control_point = curve.control_points[first + idx];
+            let weight = if curve.free_form_type.rational { self.weight(control_point) } else { 1.0 } * value;
+            point = point + self.vertex_buffer[control_point as usize] * weight;
+            weight_sum += weight;
+        }
+
+        if weight_sum != 0.0 { point * (1.0 / weight_sum) } else { point }
+    }
+
+    fn evaluate_curve_2d(geometry: &ObjFreeFormGeometry, curve: &ObjCurve2, basis: &mut Basis, t: f32) -> Vec2
+    {
+        let (first, values) = basis.evaluate(t);
+        let (mut u, mut v, mut weight_sum) = (0.0, 0.0, 0.0);
+        for (idx, value) in values.iter().enumerate()
+        {
+            let control_point = geometry.parameter_vertices[curve.control_points[first + idx] as usize];
+            let weight = if curve.free_form_type.rational { control_point.z } else { 1.0 } * value;
+            u += control_point.x * weight;
+            v += control_point.y * weight;
+            weight_sum += weight;
+        }
+
+        if weight_sum != 0.0 { Vec2::new(u / weight_sum, v / weight_sum) } else { Vec2::new(u, v) }
+    }
+
+    fn evaluate_surface(&self, surface: &ObjSurface, basis_u: &mut Basis, basis_v: &mut Basis, u: f32, v: f32) -> Vec3
+    {
+        let count_u = basis_u.count;
+        let (first_u, values_u) = basis_u.evaluate(u);
+        let (first_v, values_v) = basis_v.evaluate(v);
+        let mut point = Vec3::new(0.0, 0.0, 0.0);
+        let mut weight_sum = 0.0;
+        for (j, value_v) in values_v.iter().enumerate()
+        {
+            for (i, value_u) in values_u.iter().enumerate()
+            {
+                let control_point = surface.control_points[(first_v + j) * count_u + first_u + i].position_index;
+                let weight = if surface.free_form_type.rational { self.weight(control_point) } else { 1.0 } * value_u * value_v;
+                point = point + self.vertex_buffer[control_point as usize] * weight;
+                weight_sum += weight;
+            }
+        }
+
+        if weight_sum != 0.0 { point * (1.0 / weight_sum) } else { point }
+    }
+
+    // samples the trim loops of a surface as polygons in parameter space
+    fn sample_trim_loops(geometry: &ObjFreeFormGeometry, trim_loops: &[Vec<ObjTrimCurve>], samples: u32) -> Vec<Vec<Vec2>>
+    {
+        trim_loops.iter().map(|trim_loop|
+        {
+            let mut points = vec![];
+            for trim_curve in trim_loop.iter()
+            {
+                let curve = &geometry.curves_2d[trim_curve.curve_index as usize];
+                if let Some(mut basis) = Basis::new(curve.free_form_type, curve.degree, &curve.parameters, curve.control_points.len())
+                {
+                    for idx in 0..=samples
+                    {
+                        let t = trim_curve.start + (trim_curve.end - trim_curve.start) * idx as f32 / samples as f32;
+                        points.push(Self::evaluate_curve_2d(geometry, curve, &mut basis, t));
+                    }
+                }
+            }
+
+            points
+        }).collect()
+    }
+
+    // evaluates the bezier and b-spline curves and surfaces (rational or not) with the given number of segments per direction,
+    // curves are added as lines, and surfaces as triangles to their objects
+    // the weights of rational curves and surfaces are only available if ObjParseFeatures::LOAD_VERTEX_WEIGHTS is set
+    // segments is clamped to 1..=MAX_TESSELLATION_SEGMENTS
+    pub fn tessellate_free_form(&mut self, segments: u32)
+    {
+        let geometry = if let Some(geometry) = self.free_form.take() { geometry } else { return; };
+        // the vertex indices of the surface grids have to fit into u32
+        let segments = segments.clamp(1, MAX_TESSELLATION_SEGMENTS);
+
+        for curve in geometry.curves.iter()
+        {
+            let mut basis = if let Some(basis) = Basis::new(curve.free_form_type, curve.degree, &curve.parameters, curve.control_points.len())
+            {
+                basis
+            }
+            else
+            {
+                continue;
+            };
+
+            let mut line = Vec::<ObjVertexAbsolute>::with_capacity(segments as usize + 1);
+            for idx in 0..=segments
+            {
+                let t = curve.start + (curve.end - curve.start) * idx as f32 / segments as f32;
+                let position = self.evaluate_curve(curve, &mut basis, t);
+                line.push(ObjVertexAbsolute { position_index: self.push_tessellated_vertex(position), texcoord_index: None, normal_index: None });
+            }
+
+            self.objects[curve.object_index as usize].push_line(&line, curve.material_index);
+        }
+
+        for surface in geometry.surfaces.iter()
+        {
+            self.tessellate_surface(&geometry, surface, segments);
+        }
+
+        self.free_form = Some(geometry);
+    }
+
+    fn tessellate_surface(&mut self, geometry: &ObjFreeFormGeometry, surface: &ObjSurface, segments: u32)
+    {
+        let count_u = match surface.free_form_type.basis
+        {
+            ObjBasis::BSpline => surface.parameters_u.len().saturating_sub(surface.degree_u as usize + 1),
+            _ => surface.degree_u as usize * surface.parameters_u.len().max(2).saturating_sub(1) + 1
+        };
+
+        let count_v = surface.control_points.len().checked_div(count_u).unwrap_or(0);
+        if count_v == 0 || count_u * count_v != surface.control_points.len()
+        {
+            return;
+        }
+        let (mut basis_u, mut basis_v) = match (
+            Basis::new(surface.free_form_type, surface.degree_u, &surface.parameters_u, count_u),
+            Basis::new(surface.free_form_type, surface.degree_v, &surface.parameters_v, count_v))
+        {
+            (Some(basis_u), Some(basis_v)) => (basis_u, basis_v),
+            _ => return
+        };
+
+        let trims = Self::sample_trim_loops(geometry, &surface.trims, segments * 4);
+        let holes = Self::sample_trim_loops(geometry, &surface.holes, segments * 4);
+
+        let parameter = |i: u32, j: u32| Vec2::new(
+            surface.start_u + (surface.end_u - surface.start_u) * i as f32 / segments as f32,
+            surface.start_v + (surface.end_v - surface.start_v) * j as f32 / segments as f32);
+
+        // the normals are calculated from the partial derivatives, approximated with central differences
+        let delta_u = (surface.end_u - surface.start_u) * 1e-3;
+        let delta_v = (surface.end_v - surface.start_v) * 1e-3;
+        let clamp_u = |u: f32| u.max(surface.start_u.min(surface.end_u)).min(surface.start_u.max(surface.end_u));
+        let clamp_v = |v: f32| v.max(surface.start_v.min(surface.end_v)).min(surface.start_v.max(surface.end_v));
+
+        let mut grid = Vec::<ObjVertexAbsolute>::with_capacity((segments as usize + 1) * (segments as usize + 1));
+        for j in 0..=segments
+        {
+            for i in 0..=segments
+            {
+                let uv = parameter(i, j);
+                let position = self.evaluate_surface(surface, &mut basis_u, &mut basis_v, uv.x, uv.y);
+                let position_index = self.push_tessellated_vertex(position);
+
+                let texcoord_index = self.texcoord_buffer.as_mut().map(|texcoords|
+                {
+                    texcoords.push(Vec2::new(i as f32 / segments as f32, j as f32 / segments as f32));
+                    texcoords.len() as u32 - 1
+                });
+
+                if let (Some(_), Some(texcoords_w)) = (texcoord_index, &mut self.texcoord_w_buffer)
+                {
+                    texcoords_w.push(0.0);
+                }
+
+                let normal = self.normal_buffer.as_ref().map(|_|
+                {
+                    let derivative_u = self.evaluate_surface(surface, &mut basis_u, &mut basis_v, clamp_u(uv.x + delta_u), uv.y) -
+                        self.evaluate_surface(surface, &mut basis_u, &mut basis_v, clamp_u(uv.x - delta_u), uv.y);
+                    let derivative_v = self.evaluate_surface(surface, &mut basis_u, &mut basis_v, uv.x, clamp_v(uv.y + delta_v)) -
+                        self.evaluate_surface(surface, &mut basis_u, &mut basis_v, uv.x, clamp_v(uv.y - delta_v));
+                    derivative_u.cross(derivative_v).normalized().unwrap_or(Vec3::new(0.0, 0.0, 1.0))
+                });
+
+                let normal_index = match (normal, &mut self.normal_buffer)
+                {
+                    (Some(normal), Some(normals)) =>
+                    {
+                        normals.push(normal);
+                        Some(normals.len() as u32 - 1)
+                    },
+                    _ => None
+                };
+
+                grid.push(ObjVertexAbsolute { position_index, texcoord_index, normal_index });
+            }
+        }
+
+        let is_inside = |point: Vec2|
+            (trims.is_empty() || trims.iter().any(|trim_loop| is_point_in_loop(point, trim_loop))) &&
+            !holes.iter().any(|hole| is_point_in_loop(point, hole));
+
+        let object = &mut self.objects[surface.object_index as usize];
+        let row = segments as usize + 1;
+        for j in 0..segments
+        {
+            for i in 0..segments
+            {
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                for triangle in [[0, 1, 2], [0, 2, 3]].iter()
+                {
+                    let points = triangle.iter().map(|corner| parameter(corners[*corner].0, corners[*corner].1)).collect::<Vec<Vec2>>();
+                    let center = Vec2::new((points[0].x + points[1].x + points[2].x) / 3.0, (points[0].y + points[1].y + points[2].y) / 3.0);
+                    if !is_inside(center)
+                    {
+                        continue;
+                    }
+
+                    let vertex = |corner: usize| grid[corners[corner].1 as usize * row + corners[corner].0 as usize];
+                    object.push_triangle(Vector3::new(vertex(triangle[0]), vertex(triangle[1]), vertex(triangle[2])), surface.material_index, &[], None);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn load(source: &str) -> ObjParseResult
+    {
+        load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL).unwrap()
+    }
+
+    // tessellates the only curve into 2 segments, and returns the vertex at the middle of the line
+    fn curve_midpoint(source: &str) -> Vec3
+    {
+        let mut result = load(source);
+        result.tessellate_free_form(2);
+
+        let object = &result.objects[0];
+        let line_vertices = object.line_vertices(&object.lines[0]);
+        assert_eq!(line_vertices.len(), 3);
+        result.vertex_buffer[line_vertices[1].position_index as usize]
+    }
+
+    #[test]
+    fn bezier_curve_midpoint()
+    {
+        let midpoint = curve_midpoint("v 0 0 0\nv 1 2 0\nv 2 0 0\ncstype bezier\ndeg 2\ncurv 0 1 1 2 3\nparm u 0 1\nend\n");
+        assert_vec3_eq(midpoint, Vec3::new(1.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn rational_bezier_curve_midpoint()
+    {
+        // a quarter circle
+        let weight = std::f32::consts::FRAC_1_SQRT_2;
+        let source = format!("v 1 0 0\nv 1 1 0 {}\nv 0 1 0\ncstype rat bezier\ndeg 2\ncurv 0 1 1 2 3\nparm u 0 1\nend\n", weight);
+        assert_vec3_eq(curve_midpoint(&source), Vec3::new(weight, weight, 0.0));
+    }
+
+    #[test]
+    fn incomplete_trim_curve()
+    {
+        let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\nvp 0 0\nvp 1 0\ncstype bezier\ndeg 1 1\ncurv2 1 2\nparm u 0 1\nend\n\
+            surf 0 1 0 1 1 2 3 4\nparm u 0 1\nparm v 0 1\ntrim 0 1 1 0 1\nend\n";
+        match load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL)
+        {
+            Err(ObjError::InvalidArgument(location)) => assert_eq!((location.line, location.column), (15, 12)),
+            _ => panic!("expected an invalid argument")
+        };
+    }
+}
@@ -0,0 +1,180 @@
+use super::error::*;
+
+// the line which is currently being parsed, the tokens must be slices of the line
+#[derive(Copy, Clone)]
+pub(super) struct SourceLine<'a>
+{
+    pub number: usize,
+    pub bytes: &'a [u8],
+    // if the line was continued with a backslash, the offsets in bytes where the following lines start
+    pub continuations: &'a [usize]
+}
+
+impl<'a> SourceLine<'a>
+{
+    pub fn location(&self, token: &[u8]) -> ObjErrorLocation
+    {
+        let offset = token.as_ptr() as usize - self.bytes.as_ptr() as usize;
+        let continued_lines = self.continuations.iter().take_while(|start| **start <= offset).count();
+        let line_start = if continued_lines > 0 { self.continuations[continued_lines - 1] } else { 0 };
+        ObjErrorLocation
+        {
+            line: self.number + continued_lines,
+            column: offset - line_start + 1,
+            token: String::from_utf8_lossy(token).into_owned()
+        }
+    }
+}
+
+// splits on \n, \r\n and \r, and returns the 1-based line numbers
+fn split_lines(bytes: &[u8]) -> impl Iterator<Item = (usize, &[u8])>
+{
+    let mut remaining = bytes;
+    let mut number = 0;
+    std::iter::from_fn(move ||
+    {
+        if remaining.is_empty()
+        {
+            return None;
+        }
+
+        number += 1;
+        let end = remaining.iter().position(|ch| *ch == b'\n' || *ch == b'\r').unwrap_or(remaining.len());
+        let line = &remaining[..end];
+
+        let separator_length = match &remaining[end..]
+        {
+            [b'\r', b'\n', ..] => 2,
+            [] => 0,
+            _ => 1
+        };
+
+        remaining = &remaining[end + separator_length..];
+        Some((number, line))
+    })
+}
+
+// returns the line without the backslash if the line is continued on the next line
+// comments are never continued, so that e.g. a windows path at the end of a comment doesn't hide the next statement
+fn strip_continuation(line: &[u8]) -> Option<&[u8]>
+{
+    let end = line.iter().rposition(|ch| !ch.is_ascii_whitespace())?;
+    let start = line.iter().position(|ch| !ch.is_ascii_whitespace())?;
+    if line[end] == b'\\' && line[start] != b'#'
+    {
+        Some(&line[..end])
+    }
+    else
+    {
+        None
+    }
+}
+
+// returns the lines of a file, lines ending with a backslash are joined with the next line
+pub(super) struct LineReader<'a, Lines>
+where
+    Lines: Iterator<Item = (usize, &'a [u8])>
+{
+    lines: Lines,
+    joined_line: Vec<u8>,
+    continuations: Vec<usize>
+}
+
+pub(super) fn read_lines(bytes: &[u8]) -> LineReader<'_, impl Iterator<Item = (usize, &[u8])>>
+{
+    LineReader { lines: split_lines(bytes), joined_line: vec![], continuations: vec![] }
+}
+
+impl<'a, Lines> LineReader<'a, Lines>
+where
+    Lines: Iterator<Item = (usize, &'a [u8])>
+{
+    pub fn next_line(&mut self) -> Option<SourceLine<'_>>
+    {
+        let (number, line) = self.lines.next()?;
+        let mut continued = if let Some(continued) = strip_continuation(line)
+        {
+            continued
+        }
+        else
+        {
+            return Some(SourceLine { number, bytes: line, continuations: &[] });
+        };
+
+        self.joined_line.clear();
+        self.continuations.clear();
+        loop
+        {
+            // the backslash is replaced with a space, so that the columns of the first line don't change
+            self.joined_line.extend_from_slice(continued);
+            self.joined_line.push(b' ');
+            self.continuations.push(self.joined_line.len());
+
+            let next_line = if let Some((_, next_line)) = self.lines.next() { next_line } else { break; };
+            if let Some(next_continued) = strip_continuation(next_line)
+            {
+                continued = next_continued;
+            }
+            else
+            {
+                self.joined_line.extend_from_slice(next_line);
+                break;
+            }
+        }
+
+        Some(SourceLine { number, bytes: &self.joined_line, continuations: &self.continuations })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use super::super::obj::{load_obj_from_bytes, ObjParseFeatures};
+
+    // the joined lines with their first line numbers
+    fn joined_lines(bytes: &[u8]) -> Vec<(usize, String)>
+    {
+        let mut lines = read_lines(bytes);
+        let mut joined = vec![];
+        while let Some(line) = lines.next_line()
+        {
+            joined.push((line.number, String::from_utf8_lossy(line.bytes).into_owned()));
+        }
+
+        joined
+    }
+
+    #[test]
+    fn continued_lines()
+    {
+        let lines = joined_lines(b"v 0 0 0\r\nf 1 2 \\\r\n  3 \\ \r\n 4\r\ng a\n");
+        assert_eq!(lines, vec![
+            (1, "v 0 0 0".to_string()),
+            (2, "f 1 2    3   4".to_string()),
+            (5, "g a".to_string())
+        ]);
+    }
+
+    #[test]
+    fn comments_are_not_continued()
+    {
+        let lines = joined_lines(b"# c:\\dir\\\nv 0 0 0\n");
+        assert_eq!(lines, vec![(1, "# c:\\dir\\".to_string()), (2, "v 0 0 0".to_string())]);
+    }
+
+    #[test]
+    fn error_in_continued_face()
+    {
+        let source = b"v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 \\\r\n  9\n";
+        match load_obj_from_bytes(source, ObjParseFeatures::LOAD_ALL)
+        {
+            Err(ObjError::IndexOutOfBounds { location, index, .. }) =>
+            {
+                assert_eq!(index, 9);
+                assert_eq!((location.line, location.column, location.token.as_str()), (5, 3, "9"));
+            },
+            _ => panic!("expected an index out of bounds error")
+        };
+    }
+}
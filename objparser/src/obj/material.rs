@@ -1,3 +1,10 @@
+use super::error::*;
+use super::lines::*;
+use super::resolver::*;
+use super::obj::{try_parse_f32, try_parse_i32, get_statement_argument, split_whitespace, Vector3};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 
 pub struct Color
 {
@@ -14,17 +21,240 @@ impl Default for Color
     }
 }
 
+// -imfchan, the channel which is used for scalar textures (e.g. bump maps)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ObjTextureChannel
+{
+    Red,
+    Green,
+    Blue,
+    Matte,
+    Luminance,
+    Depth
+}
+
+// the options before the file name in texture statements, e.g. map_Kd -o 0.5 0.5 -s 2 2 texture.png
+pub struct ObjTextureOptions
+{
+    // -blendu on|off, -blendv on|off
+    pub blend_u: bool,
+    pub blend_v: bool,
+    // -bm mult, only used for bump maps
+    pub bump_multiplier: f32,
+    // -boost value
+    pub boost: f32,
+    // -cc on|off
+    pub color_correction: bool,
+    // -clamp on|off
+    pub clamp: bool,
+    // -imfchan r|g|b|m|l|z
+    pub channel: Option<ObjTextureChannel>,
+    // -mm base gain
+    pub base: f32,
+    pub gain: f32,
+    // -o u v w, -s u v w, -t u v w, v and w are optional
+    pub offset: Vector3<f32>,
+    pub scale: Vector3<f32>,
+    pub turbulence: Vector3<f32>,
+    // -texres resolution
+    pub resolution: Option<u32>,
+    // -type, only used for reflection maps (sphere, cube_top, cube_bottom, cube_front, cube_back, cube_left, cube_right)
+    pub reflection_type: Option<String>
+}
+
+impl Default for ObjTextureOptions
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            blend_u: true,
+            blend_v: true,
+            bump_multiplier: 1.0,
+            boost: 0.0,
+            color_correction: false,
+            clamp: false,
+            channel: None,
+            base: 0.0,
+            gain: 1.0,
+            offset: Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+            scale: Vector3 { x: 1.0, y: 1.0, z: 1.0 },
+            turbulence: Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+            resolution: None,
+            reflection_type: None
+        }
+    }
+}
+
+// a texture file, which is shared by all textures with the same resolved path
+// the file is only read when the data is requested for the first time
+pub struct ObjTextureFile
+{
+    // the directory of the material library and the file name, used if the file wasn't found when the library was loaded
+    base_directory: PathBuf,
+    file_name: String,
+    path: Option<PathBuf>,
+    resolver: Arc<dyn ObjFileResolver>,
+    data: OnceLock<Vec<u8>>
+}
+
+impl ObjTextureFile
+{
+    fn new(base_directory: &Path, file_name: &str, path: Option<PathBuf>, resolver: Arc<dyn ObjFileResolver>) -> Self
+    {
+        Self { base_directory: base_directory.into(), file_name: file_name.into(), path, resolver, data: OnceLock::new() }
+    }
+
+    // the path from the resolver, canonical for files in the file system
+    // None if the file wasn't found, data returns the error in this case
+    pub fn path(&self) -> Option<&Path>
+    {
+        self.path.as_deref()
+    }
+
+    // the directory of the material library and the file name in the texture statement, which are resolved to the path
+    pub fn base_directory(&self) -> &Path
+    {
+        &self.base_directory
+    }
+
+    pub fn file_name(&self) -> &str
+    {
+        &self.file_name
+    }
+
+    pub fn is_loaded(&self) -> bool
+    {
+        self.data.get().is_some()
+    }
+
+    pub fn data(&self) -> std::io::Result<&[u8]>
+    {
+        if let Some(data) = self.data.get()
+        {
+            return Ok(data.as_slice());
+        }
+
+        let data = match &self.path
+        {
+            Some(path) => self.resolver.read(path)?,
+            // the file may have been added after the material library was loaded
+            None => self.resolver.read(&self.resolver.resolve(&self.base_directory, &self.file_name)?)?
+        };
+
+        Ok(self.data.get_or_init(|| data).as_slice())
+    }
+}
+
 pub struct ObjTexture
 {
-    pub data: Vec<u8>
+    // the file name as it is in the material library
+    pub file_name: String,
+    pub options: ObjTextureOptions,
+    pub file: Arc<ObjTextureFile>
 }
 
 impl ObjTexture
 {
     pub fn from_file_path(path: &str) -> Result<ObjTexture, Box<dyn std::error::Error>>
     {
-        Ok(ObjTexture { data: std::fs::read(path)? })
+        let resolver = Arc::new(ObjFileSystemResolver::new());
+        let file = Arc::new(ObjTextureFile::new(Path::new(""), path, Some(resolver.resolve_root(path)?), resolver));
+        Ok(ObjTexture { file_name: path.into(), options: Default::default(), file })
     }
+
+    pub fn path(&self) -> Option<&Path>
+    {
+        self.file.path()
+    }
+
+    // reads the file if it is not loaded yet
+    pub fn data(&self) -> std::io::Result<&[u8]>
+    {
+        self.file.data()
+    }
+}
+
+// finds the files with a resolver, and keeps track of the textures which were already found
+// use the same loader for multiple files to share their textures
+pub struct ObjTextureLoader
+{
+    resolver: Arc<dyn ObjFileResolver>,
+    textures: HashMap<PathBuf, Arc<ObjTextureFile>>
+}
+
+impl Default for ObjTextureLoader
+{
+    fn default() -> Self
+    {
+        Self::with_resolver(Arc::new(ObjFileSystemResolver::new()))
+    }
+}
+
+impl ObjTextureLoader
+{
+    // uses the file system
+    pub fn new() -> Self
+    {
+        Default::default()
+    }
+
+    // uses the file system, the search directories are searched after the directory of the material library
+    pub fn with_search_directories(search_directories: Vec<PathBuf>) -> Self
+    {
+        Self::with_resolver(Arc::new(ObjFileSystemResolver::with_search_directories(search_directories)))
+    }
+
+    pub fn with_resolver(resolver: Arc<dyn ObjFileResolver>) -> Self
+    {
+        Self { resolver, textures: HashMap::new() }
+    }
+
+    pub fn resolver(&self) -> &Arc<dyn ObjFileResolver>
+    {
+        &self.resolver
+    }
+
+    // all textures found so far
+    pub fn textures(&self) -> impl Iterator<Item = &Arc<ObjTextureFile>>
+    {
+        self.textures.values()
+    }
+
+    // textures which are not found are kept, so a missing texture doesn't prevent loading the materials
+    pub fn find(&mut self, base_directory: &Path, file_name: &str) -> Arc<ObjTextureFile>
+    {
+        let path = self.resolver.resolve(base_directory, file_name).ok();
+        let key = path.clone().unwrap_or_else(|| base_directory.join(file_name));
+        let resolver = &self.resolver;
+        self.textures.entry(key)
+            .or_insert_with(|| Arc::new(ObjTextureFile::new(base_directory, file_name, path, resolver.clone())))
+            .clone()
+    }
+}
+
+// the pbr extension of the mtl format, the values are None if they are not in the material library
+#[derive(Default)]
+pub struct ObjPbrMaterial
+{
+    // Pr
+    pub roughness: Option<f32>,
+    // Pm
+    pub metallic: Option<f32>,
+    // Ps
+    pub sheen: Option<f32>,
+    // Pc, Pcr
+    pub clearcoat_thickness: Option<f32>,
+    pub clearcoat_roughness: Option<f32>,
+    // aniso, anisor
+    pub anisotropy: Option<f32>,
+    pub anisotropy_rotation: Option<f32>,
+
+    pub roughness_texture: Option<ObjTexture>,
+    pub metallic_texture: Option<ObjTexture>,
+    pub sheen_texture: Option<ObjTexture>,
+    // norm, a tangent space normal map
+    pub normal_map: Option<ObjTexture>
 }
 
 pub struct ObjMaterial
@@ -35,11 +265,28 @@ pub struct ObjMaterial
     pub ambient_color: Color,
     pub diffuse_color: Color,
     pub specular_color: Color,
+    // false if the material has no Ks statement, specular_color is the default value then
+    pub has_specular_color: bool,
     pub specular_exponent: f32,
+    pub emissive_color: Color,
+    pub transmission_filter: Color,
+    // index of refraction
+    pub optical_density: f32,
+    // illum, None if not specified
+    pub illumination_model: Option<u32>,
 
     pub ambient_texture: Option<ObjTexture>,
     pub diffuse_texture: Option<ObjTexture>,
-    pub bump_map: Option<ObjTexture>
+    pub specular_texture: Option<ObjTexture>,
+    pub specular_exponent_texture: Option<ObjTexture>,
+    pub alpha_texture: Option<ObjTexture>,
+    pub bump_map: Option<ObjTexture>,
+    pub displacement_map: Option<ObjTexture>,
+    pub decal_texture: Option<ObjTexture>,
+    // a single sphere map, or up to 6 cube map faces, see ObjTextureOptions::reflection_type
+    pub reflection_maps: Vec<ObjTexture>,
+
+    pub pbr: ObjPbrMaterial
 }
 
 impl ObjMaterial
@@ -58,10 +305,505 @@ impl ObjMaterial
             ambient_color: Default::default(),
             diffuse_color: Default::default(),
             specular_color: Default::default(),
+            has_specular_color: false,
             specular_exponent: 1.0,
+            emissive_color: Color { r: 0.0, g: 0.0, b: 0.0 },
+            transmission_filter: Default::default(),
+            optical_density: 1.0,
+            illumination_model: None,
             ambient_texture: None,
             diffuse_texture: None,
+            specular_texture: None,
+            specular_exponent_texture: None,
+            alpha_texture: None,
             bump_map: None,
+            displacement_map: None,
+            decal_texture: None,
+            reflection_maps: vec![],
+            pbr: Default::default()
         }
     }
-}
\ No newline at end of file
+
+    // the roughness and metallic values of the pbr extension, the missing values are approximated from the phong parameters
+    // the roughness is derived from Ns (the blinn-phong exponent), the metallic value from the difference between Ks and Kd,
+    // materials without Ks are not metallic
+    pub fn roughness_metallic(&self) -> (f32, f32)
+    {
+        let roughness = self.pbr.roughness.unwrap_or_else(||
+        {
+            // the beckmann alpha of the blinn-phong exponent, the roughness is the square root of alpha
+            let alpha = (2.0 / (self.specular_exponent.max(0.0) + 2.0)).sqrt();
+            alpha.sqrt()
+        });
+
+        let metallic = self.pbr.metallic.unwrap_or_else(||
+        {
+            if self.has_specular_color { solve_metallic(&self.diffuse_color, &self.specular_color) } else { 0.0 }
+        });
+
+        (roughness.clamp(0.0, 1.0), metallic.clamp(0.0, 1.0))
+    }
+}
+
+// the reflectance of dielectrics in the metallic workflow
+const DIELECTRIC_SPECULAR: f32 = 0.04;
+
+fn perceived_brightness(color: &Color) -> f32
+{
+    (0.299 * color.r * color.r + 0.587 * color.g * color.g + 0.114 * color.b * color.b).sqrt()
+}
+
+// the usual conversion from the specular to the metallic workflow, solves the metallic value which results in
+// the same brightness of the diffuse and specular reflections
+fn solve_metallic(diffuse_color: &Color, specular_color: &Color) -> f32
+{
+    let diffuse = perceived_brightness(diffuse_color);
+    let specular = perceived_brightness(specular_color);
+    if specular < DIELECTRIC_SPECULAR
+    {
+        return 0.0;
+    }
+
+    let one_minus_specular_strength = 1.0 - specular_color.r.max(specular_color.g).max(specular_color.b);
+
+    let a = DIELECTRIC_SPECULAR;
+    let b = diffuse * one_minus_specular_strength / (1.0 - DIELECTRIC_SPECULAR) + specular - 2.0 * DIELECTRIC_SPECULAR;
+    let c = DIELECTRIC_SPECULAR - specular;
+    let discriminant = (b * b - 4.0 * a * c).max(0.0);
+    (-b + discriminant.sqrt()) / (2.0 * a)
+}
+
+fn read_color<'a, Iter>(source_line: SourceLine, cmd: &[u8], params_iter: &mut Iter) -> Result<Color, ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    // possible formats:
+    // Kd r
+    // Kd r g b
+    // Kd xyz x y z
+    // Kd spectral file.rfl factor
+    // g and b are optional, if both are missing r is used for all channels
+    // spectral curves are not supported, the default color is used instead
+    let mut color = [0f32; 3];
+    let mut count = 0;
+
+    for segment in params_iter
+    {
+        match segment
+        {
+            b"spectral" if count == 0 => return Ok(Color::default()),
+            b"xyz" if count == 0 => continue,
+            _ => { }
+        };
+
+        color[count] = try_parse_f32(source_line, segment)?;
+        count += 1;
+        if count == 3
+        {
+            break;
+        }
+    }
+
+    match count
+    {
+        0 => Err(ObjError::TooFewValues { location: source_line.location(cmd), expected: 1, found: 0 }),
+        1 => Ok(Color { r: color[0], g: color[0], b: color[0] }),
+        2 => Err(ObjError::TooFewValues { location: source_line.location(cmd), expected: 3, found: 2 }),
+        _ => Ok(Color { r: color[0], g: color[1], b: color[2] })
+    }
+}
+
+fn read_single_value<'a, Iter>(source_line: SourceLine, cmd: &[u8], params_iter: &mut Iter) -> Result<f32, ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    if let Some(segment) = params_iter.next()
+    {
+        try_parse_f32(source_line, segment)
+    }
+    else
+    {
+        Err(ObjError::TooFewValues { location: source_line.location(cmd), expected: 1, found: 0 })
+    }
+}
+
+// reads 1 to 3 values, the missing values keep their defaults
+fn read_texture_vector<'a, Iter>(source_line: SourceLine, option: &[u8], params_iter: &mut std::iter::Peekable<Iter>, vector: &mut Vector3<f32>)
+    -> Result<(), ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    let mut values = [vector.x, vector.y, vector.z];
+    let mut count = 0;
+    while count < 3
+    {
+        // the file name can follow directly after the values
+        match params_iter.peek().map(|segment| try_parse_f32(source_line, segment))
+        {
+            Some(Ok(value)) =>
+            {
+                values[count] = value;
+                count += 1;
+                params_iter.next();
+            },
+            _ => break
+        };
+    }
+
+    if count == 0
+    {
+        return Err(ObjError::TooFewValues { location: source_line.location(option), expected: 1, found: 0 });
+    }
+
+    *vector = Vector3 { x: values[0], y: values[1], z: values[2] };
+    Ok(())
+}
+
+fn read_on_off(source_line: SourceLine, option: &[u8], value: Option<&[u8]>) -> Result<bool, ObjError>
+{
+    match value
+    {
+        Some(b"on") => Ok(true),
+        Some(b"off") => Ok(false),
+        Some(value) => Err(ObjError::InvalidArgument(source_line.location(value))),
+        None => Err(ObjError::MissingArgument(source_line.location(option)))
+    }
+}
+
+// reads the options and the file name of a texture statement, the file name is the rest of the line, and can contain spaces
+fn read_texture_statement<'a, Iter>(source_line: SourceLine<'a>, cmd: &[u8], params_iter: Iter) -> Result<(ObjTextureOptions, &'a [u8]), ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    let mut options = ObjTextureOptions::default();
+    let mut params_iter = params_iter.peekable();
+
+    while let Some(segment) = params_iter.next()
+    {
+        if !segment.starts_with(b"-")
+        {
+            let start = segment.as_ptr() as usize - source_line.bytes.as_ptr() as usize;
+            let file_name = &source_line.bytes[start..];
+            let end = file_name.iter().rposition(|ch| !ch.is_ascii_whitespace()).map_or(0, |idx| idx + 1);
+            return Ok((options, &file_name[..end]));
+        }
+
+        let mut read_value = || -> Result<f32, ObjError>
+        {
+            let value = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(segment)))?;
+            try_parse_f32(source_line, value)
+        };
+
+        match segment
+        {
+            b"-blendu" => options.blend_u = read_on_off(source_line, segment, params_iter.next())?,
+            b"-blendv" => options.blend_v = read_on_off(source_line, segment, params_iter.next())?,
+            b"-cc" => options.color_correction = read_on_off(source_line, segment, params_iter.next())?,
+            b"-clamp" => options.clamp = read_on_off(source_line, segment, params_iter.next())?,
+            b"-bm" => options.bump_multiplier = read_value()?,
+            b"-boost" => options.boost = read_value()?,
+            b"-mm" =>
+            {
+                options.base = read_value()?;
+                options.gain = read_value()?;
+            },
+            b"-o" => read_texture_vector(source_line, segment, &mut params_iter, &mut options.offset)?,
+            b"-s" => read_texture_vector(source_line, segment, &mut params_iter, &mut options.scale)?,
+            b"-t" => read_texture_vector(source_line, segment, &mut params_iter, &mut options.turbulence)?,
+            b"-texres" =>
+            {
+                let value = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(segment)))?;
+                let resolution = try_parse_i32(source_line, value)?;
+                if resolution <= 0
+                {
+                    return Err(ObjError::InvalidArgument(source_line.location(value)));
+                }
+
+                options.resolution = Some(resolution as u32);
+            },
+            b"-imfchan" =>
+            {
+                let value = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(segment)))?;
+                options.channel = Some(match value
+                {
+                    b"r" => ObjTextureChannel::Red,
+                    b"g" => ObjTextureChannel::Green,
+                    b"b" => ObjTextureChannel::Blue,
+                    b"m" => ObjTextureChannel::Matte,
+                    b"l" => ObjTextureChannel::Luminance,
+                    b"z" => ObjTextureChannel::Depth,
+                    _ => return Err(ObjError::InvalidArgument(source_line.location(value)))
+                });
+            },
+            b"-type" =>
+            {
+                let value = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(segment)))?;
+                options.reflection_type = Some(String::from_utf8_lossy(value).into_owned());
+            },
+            _ => return Err(ObjError::InvalidArgument(source_line.location(segment)))
+        };
+    }
+
+    Err(ObjError::MissingArgument(source_line.location(cmd)))
+}
+
+struct MtlContext<'a>
+{
+    base_directory: &'a Path,
+    texture_loader: &'a mut ObjTextureLoader
+}
+
+fn load_texture<'a, Iter>(source_line: SourceLine<'a>, cmd: &[u8], params_iter: Iter, context: &mut MtlContext) -> Result<ObjTexture, ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    let (options, file_name_bytes) = read_texture_statement(source_line, cmd, params_iter)?;
+
+    let file_name = std::str::from_utf8(file_name_bytes).map_err(|_| ObjError::InvalidPath(source_line.location(file_name_bytes)))?;
+    let file = context.texture_loader.find(context.base_directory, file_name);
+    Ok(ObjTexture { file_name: file_name.into(), options, file })
+}
+
+pub fn load_mtl(file_path: &str) -> Result<Vec<ObjMaterial>, ObjError>
+{
+    load_mtl_with_texture_loader(file_path, &mut ObjTextureLoader::new())
+}
+
+// the material library and the textures are loaded with the resolver of the texture loader
+pub fn load_mtl_with_texture_loader(file_path: &str, texture_loader: &mut ObjTextureLoader) -> Result<Vec<ObjMaterial>, ObjError>
+{
+    load_mtl_from_path(None, file_path, texture_loader, &mut ErrorRecovery::new(false))
+}
+
+// the parent directory is the directory of the obj file, None if the library is loaded directly
+pub(super) fn load_mtl_from_path(parent_directory: Option<&Path>, file_name: &str, texture_loader: &mut ObjTextureLoader, recovery: &mut ErrorRecovery)
+    -> Result<Vec<ObjMaterial>, ObjError>
+{
+    let resolver = texture_loader.resolver().clone();
+    let file_path = match parent_directory
+    {
+        Some(parent_directory) => resolver.resolve(parent_directory, file_name)
+            .map_err(|error| ObjError::io(&parent_directory.join(file_name), error))?,
+        None => resolver.resolve_root(file_name).map_err(|error| ObjError::io(Path::new(file_name), error))?
+    };
+
+    let file_bytes = resolver.read(&file_path).map_err(|error| ObjError::io(&file_path, error))?;
+    let base_directory = file_path.parent().unwrap_or_else(|| Path::new(""));
+    parse_mtl(file_bytes.as_slice(), &mut MtlContext { base_directory, texture_loader }, recovery)
+}
+
+pub fn load_mtl_from_bytes(file_bytes: &[u8], base_directory: &Path) -> Result<Vec<ObjMaterial>, ObjError>
+{
+    load_mtl_from_bytes_with_texture_loader(file_bytes, base_directory, &mut ObjTextureLoader::new())
+}
+
+// the textures are resolved relative to the base directory with the resolver of the texture loader
+pub fn load_mtl_from_bytes_with_texture_loader(file_bytes: &[u8], base_directory: &Path, texture_loader: &mut ObjTextureLoader)
+    -> Result<Vec<ObjMaterial>, ObjError>
+{
+    let mut context = MtlContext { base_directory, texture_loader };
+    parse_mtl(file_bytes, &mut context, &mut ErrorRecovery::new(false))
+}
+
+fn parse_mtl_statement<'a, Iter>(source_line: SourceLine<'a>, cmd: &[u8], params_iter: &mut Iter, material: &mut ObjMaterial, context: &mut MtlContext)
+    -> Result<(), ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    match cmd
+    {
+        b"Ka" => material.ambient_color = read_color(source_line, cmd, params_iter)?,
+        b"Kd" => material.diffuse_color = read_color(source_line, cmd, params_iter)?,
+        b"Ks" =>
+        {
+            material.specular_color = read_color(source_line, cmd, params_iter)?;
+            material.has_specular_color = true;
+        },
+        b"Ns" => material.specular_exponent = read_single_value(source_line, cmd, params_iter)?,
+        b"d" =>
+        {
+            // d -halo factor
+            let value = match params_iter.next()
+            {
+                Some(b"-halo") => read_single_value(source_line, cmd, params_iter)?,
+                Some(segment) => try_parse_f32(source_line, segment)?,
+                None => return Err(ObjError::TooFewValues { location: source_line.location(cmd), expected: 1, found: 0 })
+            };
+
+            material.alpha = value;
+        },
+        b"Tr" =>
+        {
+            // transparency, inverse of d
+            material.alpha = 1.0 - read_single_value(source_line, cmd, params_iter)?;
+        },
+        b"Ke" => material.emissive_color = read_color(source_line, cmd, params_iter)?,
+        b"Tf" => material.transmission_filter = read_color(source_line, cmd, params_iter)?,
+        b"Ni" => material.optical_density = read_single_value(source_line, cmd, params_iter)?,
+        b"illum" =>
+        {
+            let segment = params_iter.next().ok_or_else(|| ObjError::MissingArgument(source_line.location(cmd)))?;
+            let illumination_model = try_parse_i32(source_line, segment)?;
+            if illumination_model < 0
+            {
+                return Err(ObjError::InvalidArgument(source_line.location(segment)));
+            }
+
+            material.illumination_model = Some(illumination_model as u32);
+        },
+        b"map_Ka" => material.ambient_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"map_Kd" => material.diffuse_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"map_Ks" => material.specular_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"map_Ns" => material.specular_exponent_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"map_d" => material.alpha_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"bump" | b"map_bump" | b"map_Bump" => material.bump_map = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"disp" => material.displacement_map = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"decal" => material.decal_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"refl" => material.reflection_maps.push(load_texture(source_line, cmd, params_iter, context)?),
+        b"Pr" => material.pbr.roughness = Some(read_single_value(source_line, cmd, params_iter)?),
+        b"Pm" => material.pbr.metallic = Some(read_single_value(source_line, cmd, params_iter)?),
+        b"Ps" => material.pbr.sheen = Some(read_single_value(source_line, cmd, params_iter)?),
+        b"Pc" => material.pbr.clearcoat_thickness = Some(read_single_value(source_line, cmd, params_iter)?),
+        b"Pcr" => material.pbr.clearcoat_roughness = Some(read_single_value(source_line, cmd, params_iter)?),
+        b"aniso" => material.pbr.anisotropy = Some(read_single_value(source_line, cmd, params_iter)?),
+        b"anisor" => material.pbr.anisotropy_rotation = Some(read_single_value(source_line, cmd, params_iter)?),
+        b"map_Pr" => material.pbr.roughness_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"map_Pm" => material.pbr.metallic_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"map_Ps" => material.pbr.sheen_texture = Some(load_texture(source_line, cmd, params_iter, context)?),
+        b"norm" => material.pbr.normal_map = Some(load_texture(source_line, cmd, params_iter, context)?),
+        _ => { }
+    };
+
+    Ok(())
+}
+
+fn parse_mtl(file_bytes: &[u8], context: &mut MtlContext, recovery: &mut ErrorRecovery) -> Result<Vec<ObjMaterial>, ObjError>
+{
+    let mut materials = Vec::<ObjMaterial>::new();
+
+    let mut lines = read_lines(file_bytes);
+    while let Some(source_line) = lines.next_line()
+    {
+        let line = source_line.bytes;
+        let mut split_iter = split_whitespace(line);
+        if let Some(cmd) = split_iter.next()
+        {
+            if cmd == b"newmtl"
+            {
+                let name = String::from_utf8_lossy(get_statement_argument(line)).into_owned();
+                materials.push(ObjMaterial::new(if name.is_empty() { ObjMaterial::default_name() } else { name }));
+                continue;
+            }
+
+            let current_material = if let Some(material) = materials.last_mut()
+            {
+                material
+            }
+            else
+            {
+                // statements before the first newmtl don't belong to any material
+                continue;
+            };
+
+            if let Err(error) = parse_mtl_statement(source_line, cmd, &mut split_iter, current_material, context)
+            {
+                recovery.recover(error, ObjWarningKind::StatementSkipped)?;
+            }
+        }
+    }
+
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn load(source: &str) -> Vec<ObjMaterial>
+    {
+        load_mtl_from_bytes(source.as_bytes(), Path::new("")).unwrap()
+    }
+
+    fn assert_close(actual: f32, expected: f32)
+    {
+        assert!((actual - expected).abs() < 1e-4, "{} != {}", actual, expected);
+    }
+
+    // the perceptual roughness of a blinn-phong exponent
+    fn phong_roughness(specular_exponent: f32) -> f32
+    {
+        (2.0 / (specular_exponent + 2.0)).sqrt().sqrt()
+    }
+
+    #[test]
+    fn pbr_statements()
+    {
+        let materials = load("newmtl pbr\nPr 0.25\nPm 0.75\nPs 0.1\nPc 0.2\nPcr 0.3\naniso 0.4\nanisor 0.5\n\
+            map_Pr -bm 0.5 rough.png\nmap_Pm metal.png\nmap_Ps sheen.png\nnorm -bm 2 normal.png\n");
+        let pbr = &materials[0].pbr;
+        assert_eq!(pbr.roughness, Some(0.25));
+        assert_eq!(pbr.metallic, Some(0.75));
+        assert_eq!(pbr.sheen, Some(0.1));
+        assert_eq!(pbr.clearcoat_thickness, Some(0.2));
+        assert_eq!(pbr.clearcoat_roughness, Some(0.3));
+        assert_eq!(pbr.anisotropy, Some(0.4));
+        assert_eq!(pbr.anisotropy_rotation, Some(0.5));
+
+        assert_eq!(pbr.roughness_texture.as_ref().unwrap().file_name, "rough.png");
+        assert_eq!(pbr.roughness_texture.as_ref().unwrap().options.bump_multiplier, 0.5);
+        assert_eq!(pbr.metallic_texture.as_ref().unwrap().file_name, "metal.png");
+        assert_eq!(pbr.sheen_texture.as_ref().unwrap().file_name, "sheen.png");
+        assert_eq!(pbr.normal_map.as_ref().unwrap().file_name, "normal.png");
+        assert_eq!(pbr.normal_map.as_ref().unwrap().options.bump_multiplier, 2.0);
+
+        assert_eq!(materials[0].roughness_metallic(), (0.25, 0.75));
+    }
+
+    #[test]
+    fn missing_pbr_statements()
+    {
+        let pbr = &load("newmtl phong\nKd 0.8 0.2 0.2\n")[0].pbr;
+        assert!(pbr.roughness.is_none() && pbr.metallic.is_none() && pbr.sheen.is_none() && pbr.anisotropy.is_none());
+        assert!(pbr.normal_map.is_none());
+    }
+
+    #[test]
+    fn phong_without_specular_color()
+    {
+        // the default white specular color doesn't make the material metallic
+        let materials = load("newmtl plain\nKd 0.8 0.2 0.2\n");
+        assert!(!materials[0].has_specular_color);
+        let (roughness, metallic) = materials[0].roughness_metallic();
+        assert_close(roughness, phong_roughness(1.0));
+        assert_eq!(metallic, 0.0);
+
+        // placeholder materials of unknown usemtl statements
+        assert_eq!(ObjMaterial::new("unknown".into()).roughness_metallic().1, 0.0);
+    }
+
+    #[test]
+    fn phong_dielectric()
+    {
+        let materials = load("newmtl plastic\nKd 0.8 0.8 0.8\nKs 0.04 0.04 0.04\nNs 1000\n");
+        let (roughness, metallic) = materials[0].roughness_metallic();
+        assert_close(roughness, phong_roughness(1000.0));
+        assert_close(metallic, 0.0);
+    }
+
+    #[test]
+    fn phong_metal()
+    {
+        let materials = load("newmtl gold\nKd 0 0 0\nKs 1.0 0.77 0.33\nNs 250\n");
+        let (roughness, metallic) = materials[0].roughness_metallic();
+        assert_close(roughness, phong_roughness(250.0));
+        assert!(metallic > 0.99, "{}", metallic);
+    }
+
+    #[test]
+    fn pbr_values_override_phong()
+    {
+        let materials = load("newmtl mixed\nKd 0 0 0\nKs 1 1 1\nNs 1000\nPr 0.6\nPm 0\n");
+        assert_eq!(materials[0].roughness_metallic(), (0.6, 0.0));
+    }
+}
@@ -0,0 +1,175 @@
+use super::obj::*;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ObjIndexFormat
+{
+    U32,
+    // u16 indices are used if the mesh has at most 65536 vertices, u32 otherwise
+    U16IfPossible
+}
+
+pub enum ObjIndexBuffer
+{
+    U16(Vec<u16>),
+    U32(Vec<u32>)
+}
+
+impl ObjIndexBuffer
+{
+    pub fn len(&self) -> usize
+    {
+        match self
+        {
+            ObjIndexBuffer::U16(indices) => indices.len(),
+            ObjIndexBuffer::U32(indices) => indices.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.len() == 0
+    }
+}
+
+// a single object with one index per vertex, which can be uploaded to the gpu directly
+// the vertex attributes are stored as separate arrays, use interleaved_vertices to get a single array
+pub struct ObjMesh
+{
+    pub name: Vec<u8>,
+    pub positions: Vec<Vector3<f32>>,
+    pub texcoords: Option<Vec<Vector2<f32>>>,
+    pub normals: Option<Vec<Vector3<f32>>>,
+    pub colors: Option<Vec<Vector3<f32>>>,
+    // xyz is the tangent, w is the sign of the bitangent (bitangent = w * cross(normal, tangent))
+    // only present after generate_tangents
+    pub tangents: Option<Vec<Vector4<f32>>>,
+    // 3 indices per triangle, the triangle ranges of the submeshes are the same as in ObjObject
+    pub indices: ObjIndexBuffer,
+    pub submeshes: Vec<ObjSubmesh>
+}
+
+impl ObjMesh
+{
+    pub fn vertex_count(&self) -> usize
+    {
+        self.positions.len()
+    }
+
+    // the number of floats per vertex in interleaved_vertices
+    pub fn vertex_stride(&self) -> usize
+    {
+        3 +
+        if self.texcoords.is_some() { 2 } else { 0 } +
+        if self.normals.is_some() { 3 } else { 0 } +
+        if self.colors.is_some() { 3 } else { 0 } +
+        if self.tangents.is_some() { 4 } else { 0 }
+    }
+
+    // position, texcoord (if present), normal (if present), color (if present), tangent (if present) for each vertex
+    pub fn interleaved_vertices(&self) -> Vec<f32>
+    {
+        let mut vertices = Vec::<f32>::with_capacity(self.vertex_count() * self.vertex_stride());
+        for i in 0..self.vertex_count()
+        {
+            let position = self.positions[i];
+            vertices.extend_from_slice(&[position.x, position.y, position.z]);
+
+            if let Some(texcoords) = &self.texcoords
+            {
+                vertices.extend_from_slice(&[texcoords[i].x, texcoords[i].y]);
+            }
+
+            if let Some(normals) = &self.normals
+            {
+                vertices.extend_from_slice(&[normals[i].x, normals[i].y, normals[i].z]);
+            }
+
+            if let Some(colors) = &self.colors
+            {
+                vertices.extend_from_slice(&[colors[i].x, colors[i].y, colors[i].z]);
+            }
+
+            if let Some(tangents) = &self.tangents
+            {
+                vertices.extend_from_slice(&[tangents[i].x, tangents[i].y, tangents[i].z, tangents[i].w]);
+            }
+        }
+
+        vertices
+    }
+}
+
+impl ObjParseResult
+{
+    // creates one mesh per object, identical position/texcoord/normal combinations share the same vertex
+    pub fn build_meshes(&self, index_format: ObjIndexFormat) -> Vec<ObjMesh>
+    {
+        self.objects.iter().map(|object| self.build_mesh(object, index_format)).collect()
+    }
+
+    pub fn build_mesh(&self, object: &ObjObject, index_format: ObjIndexFormat) -> ObjMesh
+    {
+        let mut vertex_map = HashMap::<ObjVertexAbsolute, u32>::with_capacity(object.indices.len());
+        let mut positions = Vec::<Vector3<f32>>::new();
+        let mut texcoords = self.texcoord_buffer.as_ref().map(|_| Vec::<Vector2<f32>>::new());
+        let mut normals = self.normal_buffer.as_ref().map(|_| Vec::<Vector3<f32>>::new());
+        let mut colors = self.color_buffer.as_ref().map(|_| Vec::<Vector3<f32>>::new());
+        let mut indices = Vec::<u32>::with_capacity(object.indices.len() * 3);
+
+        for triangle in object.indices.iter()
+        {
+            for vertex in [triangle.x, triangle.y, triangle.z].iter()
+            {
+                let index = *vertex_map.entry(*vertex).or_insert_with(||
+                {
+                    positions.push(self.vertex_buffer[vertex.position_index as usize]);
+
+                    // vertices without a texcoord or normal index get zero values
+                    if let (Some(texcoords), Some(texcoord_buffer)) = (&mut texcoords, &self.texcoord_buffer)
+                    {
+                        texcoords.push(vertex.texcoord_index
+                            .map_or(Vector2::new(0.0, 0.0), |idx| texcoord_buffer[idx as usize]));
+                    }
+
+                    if let (Some(normals), Some(normal_buffer)) = (&mut normals, &self.normal_buffer)
+                    {
+                        normals.push(vertex.normal_index
+                            .map_or(Vector3::new(0.0, 0.0, 0.0), |idx| normal_buffer[idx as usize]));
+                    }
+
+                    // colors belong to the positions
+                    if let (Some(colors), Some(color_buffer)) = (&mut colors, &self.color_buffer)
+                    {
+                        colors.push(color_buffer[vertex.position_index as usize]);
+                    }
+
+                    positions.len() as u32 - 1
+                });
+
+                indices.push(index);
+            }
+        }
+
+        let indices = if index_format == ObjIndexFormat::U16IfPossible && positions.len() <= 65536
+        {
+            ObjIndexBuffer::U16(indices.iter().map(|idx| *idx as u16).collect())
+        }
+        else
+        {
+            ObjIndexBuffer::U32(indices)
+        };
+
+        ObjMesh
+        {
+            name: object.name.clone(),
+            positions,
+            texcoords,
+            normals,
+            colors,
+            tangents: None,
+            indices,
+            submeshes: object.submeshes.clone()
+        }
+    }
+}
@@ -1,3 +1,12 @@
+#[allow(clippy::module_inception)]
 pub mod obj;
 pub mod material;
-pub mod export;
\ No newline at end of file
+pub mod export;
+pub mod error;
+pub mod mesh;
+pub mod normals;
+pub mod freeform;
+pub mod resolver;
+mod lines;
+mod triangulation;
+mod tangents;
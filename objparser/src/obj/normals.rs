@@ -0,0 +1,302 @@
+use super::obj::*;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ObjNormalMode
+{
+    // one normal per triangle
+    Flat,
+    // one normal per position, the face normals are weighted by the area of the faces
+    SmoothAreaWeighted,
+    // one normal per position, the face normals are weighted by the angle of the faces at the vertex
+    SmoothAngleWeighted,
+    // angle weighted, but faces are only smoothed together if they are in the same smoothing group,
+    // and the angle between them is at most crease_angle (in radians)
+    // faces with smoothing off are flat, objects without any s statements are treated as a single smoothing group
+    SmoothingGroups { crease_angle: f32 }
+}
+
+// used if a vertex only has degenerate faces
+const DEFAULT_NORMAL: Vec3 = Vector3 { x: 0.0, y: 0.0, z: 1.0 };
+
+struct FaceNormals
+{
+    // zero if the face is degenerate
+    normal: Vec3,
+    // the weighted face normal for each corner
+    corners: [Vec3; 3],
+    smoothing_group: u32
+}
+
+fn face_normals(positions: [Vec3; 3], angle_weighted: bool, smoothing_group: u32) -> FaceNormals
+{
+    let cross = (positions[1] - positions[0]).cross(positions[2] - positions[0]);
+    let normal = cross.normalized().unwrap_or(Vec3::new(0.0, 0.0, 0.0));
+
+    let corners = if angle_weighted
+    {
+        let mut corners = [normal; 3];
+        for (idx, corner) in corners.iter_mut().enumerate()
+        {
+            let to_prev = (positions[(idx + 2) % 3] - positions[idx]).normalized();
+            let to_next = (positions[(idx + 1) % 3] - positions[idx]).normalized();
+            let angle = match (to_prev, to_next)
+            {
+                (Some(to_prev), Some(to_next)) => to_prev.dot(to_next).clamp(-1.0, 1.0).acos(),
+                _ => 0.0
+            };
+
+            *corner = normal * angle;
+        }
+
+        corners
+    }
+    else
+    {
+        // the length of the cross product is twice the area of the triangle
+        [cross; 3]
+    };
+
+    FaceNormals { normal, corners, smoothing_group }
+}
+
+// splits the sorted corners into runs with the same position index
+fn split_by_position(corners: &[(u32, u32, u8)]) -> Vec<&[(u32, u32, u8)]>
+{
+    let mut runs = vec![];
+    let mut start = 0;
+    for idx in 1..=corners.len()
+    {
+        if idx == corners.len() || corners[idx].0 != corners[start].0
+        {
+            runs.push(&corners[start..idx]);
+            start = idx;
+        }
+    }
+
+    runs
+}
+
+impl ObjParseResult
+{
+    // replaces normal_buffer and the normal indices of all faces, the normal indices of lines and points are removed
+    pub fn generate_normals(&mut self, mode: ObjNormalMode)
+    {
+        let angle_weighted = mode != ObjNormalMode::SmoothAreaWeighted;
+
+        let mut faces = Vec::<FaceNormals>::with_capacity(self.objects.iter().map(|object| object.indices.len()).sum());
+        for object in self.objects.iter()
+        {
+            for (triangle_index, triangle) in object.indices.iter().enumerate()
+            {
+                let positions = [
+                    self.vertex_buffer[triangle.x.position_index as usize],
+                    self.vertex_buffer[triangle.y.position_index as usize],
+                    self.vertex_buffer[triangle.z.position_index as usize]
+                ];
+
+                let smoothing_group = if object.smoothing_groups.is_empty() { 1 } else { object.smoothing_group(triangle_index) };
+                faces.push(face_normals(positions, angle_weighted, smoothing_group));
+            }
+        }
+
+        // the normal index of each corner of each face
+        let mut corner_normal_indices = vec![[0u32; 3]; faces.len()];
+        let mut normals = Vec::<Vec3>::new();
+
+        match mode
+        {
+            ObjNormalMode::Flat =>
+            {
+                for (face, indices) in faces.iter().zip(corner_normal_indices.iter_mut())
+                {
+                    *indices = [normals.len() as u32; 3];
+                    normals.push(face.normal.normalized().unwrap_or(DEFAULT_NORMAL));
+                }
+            },
+            ObjNormalMode::SmoothAreaWeighted | ObjNormalMode::SmoothAngleWeighted =>
+            {
+                normals.resize(self.vertex_buffer.len(), Vec3::new(0.0, 0.0, 0.0));
+                for (face, triangle) in faces.iter().zip(self.objects.iter().flat_map(|object| object.indices.iter()))
+                {
+                    for (corner, vertex) in [triangle.x, triangle.y, triangle.z].iter().enumerate()
+                    {
+                        let normal = &mut normals[vertex.position_index as usize];
+                        *normal = *normal + face.corners[corner];
+                    }
+                }
+
+                for normal in normals.iter_mut()
+                {
+                    *normal = normal.normalized().unwrap_or(DEFAULT_NORMAL);
+                }
+
+                for (indices, triangle) in corner_normal_indices.iter_mut().zip(self.objects.iter().flat_map(|object| object.indices.iter()))
+                {
+                    *indices = [triangle.x.position_index, triangle.y.position_index, triangle.z.position_index];
+                }
+            },
+            ObjNormalMode::SmoothingGroups { crease_angle } =>
+            {
+                let min_cos = crease_angle.cos();
+
+                // position index, face index, corner, sorted so that the corners of the same position are next to each other
+                let mut corners = Vec::<(u32, u32, u8)>::with_capacity(faces.len() * 3);
+                for (face_index, triangle) in self.objects.iter().flat_map(|object| object.indices.iter()).enumerate()
+                {
+                    for (corner, vertex) in [triangle.x, triangle.y, triangle.z].iter().enumerate()
+                    {
+                        corners.push((vertex.position_index, face_index as u32, corner as u8));
+                    }
+                }
+
+                corners.sort_unstable();
+
+                // identical normals of the same position share the same index
+                let mut normal_map = HashMap::<(u32, [u32; 3]), u32>::new();
+                for position_corners in split_by_position(&corners)
+                {
+                    for (position_index, face_index, corner) in position_corners.iter()
+                    {
+                        let face = &faces[*face_index as usize];
+                        let mut normal = face.corners[*corner as usize];
+
+                        for (_, other_face_index, other_corner) in position_corners.iter()
+                        {
+                            let other_face = &faces[*other_face_index as usize];
+                            if other_face_index != face_index &&
+                                face.smoothing_group != 0 &&
+                                face.smoothing_group == other_face.smoothing_group &&
+                                face.normal.dot(other_face.normal) >= min_cos
+                            {
+                                normal = normal + other_face.corners[*other_corner as usize];
+                            }
+                        }
+
+                        let normal = normal.normalized()
+                            .or_else(|| face.normal.normalized())
+                            .unwrap_or(DEFAULT_NORMAL);
+
+                        let key = (*position_index, [normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits()]);
+                        let normal_index = *normal_map.entry(key).or_insert_with(||
+                        {
+                            normals.push(normal);
+                            normals.len() as u32 - 1
+                        });
+
+                        corner_normal_indices[*face_index as usize][*corner as usize] = normal_index;
+                    }
+                }
+            }
+        };
+
+        let mut face_index = 0;
+        for object in self.objects.iter_mut()
+        {
+            for triangle in object.indices.iter_mut()
+            {
+                let indices = corner_normal_indices[face_index];
+                triangle.x.normal_index = Some(indices[0]);
+                triangle.y.normal_index = Some(indices[1]);
+                triangle.z.normal_index = Some(indices[2]);
+                face_index += 1;
+            }
+
+            // the polygon vertices get the normals of the same vertices in the triangles of the polygon
+            for polygon in object.polygons.iter()
+            {
+                let triangle_start = polygon.triangle_start as usize;
+                let triangles = &object.indices[triangle_start..triangle_start + polygon.vertex_count as usize - 2];
+
+                let vertex_start = polygon.vertex_start as usize;
+                for vertex in object.polygon_vertices[vertex_start..vertex_start + polygon.vertex_count as usize].iter_mut()
+                {
+                    vertex.normal_index = triangles.iter()
+                        .flat_map(|triangle| [triangle.x, triangle.y, triangle.z].to_vec())
+                        .find(|corner| corner.position_index == vertex.position_index && corner.texcoord_index == vertex.texcoord_index)
+                        .and_then(|corner| corner.normal_index);
+                }
+            }
+
+            for vertex in object.line_vertices.iter_mut().chain(object.points.iter_mut())
+            {
+                vertex.normal_index = None;
+            }
+        }
+
+        self.normal_buffer = Some(normals);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // two triangles with a 90 degree fold along the edge between vertex 1 and 2,
+    // the first one faces +z, the second one +y
+    fn folded_triangles(smoothing_groups: (&str, &str), mode: ObjNormalMode) -> ObjParseResult
+    {
+        let source = format!("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\ns {}\nf 1 2 3\ns {}\nf 2 1 4\n", smoothing_groups.0, smoothing_groups.1);
+        let mut result = load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL).unwrap();
+        result.generate_normals(mode);
+        result
+    }
+
+    // the normals of the first vertex in the first and the second triangle
+    fn first_vertex_normals(result: &ObjParseResult) -> (Vec3, Vec3)
+    {
+        let normals = result.normal_buffer.as_ref().unwrap();
+        let triangles = &result.objects[0].indices;
+        (normals[triangles[0].x.normal_index.unwrap() as usize], normals[triangles[1].y.normal_index.unwrap() as usize])
+    }
+
+    #[test]
+    fn smoothing_group_boundary()
+    {
+        let result = folded_triangles(("1", "2"), ObjNormalMode::SmoothingGroups { crease_angle: std::f32::consts::PI });
+        let (first, second) = first_vertex_normals(&result);
+        assert_vec3_eq(first, Vec3::new(0.0, 0.0, 1.0));
+        assert_vec3_eq(second, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn same_smoothing_group()
+    {
+        let result = folded_triangles(("1", "1"), ObjNormalMode::SmoothingGroups { crease_angle: std::f32::consts::PI });
+        let (first, second) = first_vertex_normals(&result);
+        let expected = Vec3::new(0.0, 1.0, 1.0).normalized().unwrap();
+        assert_vec3_eq(first, expected);
+        assert_vec3_eq(second, expected);
+    }
+
+    #[test]
+    fn crease_angle()
+    {
+        let result = folded_triangles(("1", "1"), ObjNormalMode::SmoothingGroups { crease_angle: std::f32::consts::FRAC_PI_4 });
+        let (first, second) = first_vertex_normals(&result);
+        assert_vec3_eq(first, Vec3::new(0.0, 0.0, 1.0));
+        assert_vec3_eq(second, Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn without_smoothing_groups()
+    {
+        // objects without s statements are treated as a single smoothing group
+        let mut result = load_obj_from_bytes(b"v 0 0 0\nv 1 0 0\nv 0 1 0\nv 0 0 1\nf 1 2 3\nf 2 1 4\n", ObjParseFeatures::LOAD_ALL).unwrap();
+        result.generate_normals(ObjNormalMode::SmoothingGroups { crease_angle: std::f32::consts::PI });
+        let (first, second) = first_vertex_normals(&result);
+        let expected = Vec3::new(0.0, 1.0, 1.0).normalized().unwrap();
+        assert_vec3_eq(first, expected);
+        assert_vec3_eq(second, expected);
+    }
+
+    #[test]
+    fn smoothing_off_is_flat()
+    {
+        let result = folded_triangles(("off", "off"), ObjNormalMode::SmoothingGroups { crease_angle: std::f32::consts::PI });
+        let (first, second) = first_vertex_normals(&result);
+        assert_vec3_eq(first, Vec3::new(0.0, 0.0, 1.0));
+        assert_vec3_eq(second, Vec3::new(0.0, 1.0, 0.0));
+    }
+}
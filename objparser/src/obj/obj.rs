@@ -2,41 +2,61 @@
 extern crate bitflags;
 extern crate lexical;
 
-fn format_parse_error<T>(bytes: &[u8]) -> String
+use super::error::*;
+use super::lines::*;
+use super::material::*;
+use super::triangulation::*;
+use super::freeform::*;
+use std::path::Path;
+
+pub(super) fn try_parse_f32(source_line: SourceLine, bytes: &[u8]) -> Result<f32, ObjError>
 {
-    format!("Parse error: {}",
+    match fast_float::parse::<f32, _>(bytes)
     {
-        let type_str = std::any::type_name::<T>();
-        if let Ok(s) = std::str::from_utf8(bytes.into())
-        {
-            format!("cannot parse {} as {}", s, type_str)
-        }
-        else
-        {
-            type_str.into()
-        }
-    })
+        Ok(val) => Ok(val),
+        Err(_) => Err(ObjError::ParseFloat(source_line.location(bytes)))
+    }
 }
 
-fn try_parse_f32(bytes: &[u8]) -> Result<f32, Box<dyn std::error::Error>>
+pub(super) fn try_parse_i32(source_line: SourceLine, bytes: &[u8]) -> Result<i32, ObjError>
 {
-    match fast_float::parse::<f32, _>(bytes)
+    match lexical::parse::<i32, _>(bytes)
     {
         Ok(val) => Ok(val),
-        Err(_) => Err(format_parse_error::<f32>(bytes).into())
+        Err(_) => Err(ObjError::ParseInt(source_line.location(bytes)))
     }
 }
 
-fn try_parse<T: lexical::FromLexical>(bytes: &[u8]) -> Result<T, Box<dyn std::error::Error>>
+fn trim_whitespace(bytes: &[u8]) -> &[u8]
+{
+    let start = bytes.iter().position(|ch| !ch.is_ascii_whitespace()).unwrap_or(bytes.len());
+    let end = bytes.iter().rposition(|ch| !ch.is_ascii_whitespace()).map_or(start, |pos| pos + 1);
+    &bytes[start..end]
+}
+
+// returns everything after the command, e.g. the name in "o object name"
+pub(super) fn get_statement_argument(line: &[u8]) -> &[u8]
+{
+    let line = trim_whitespace(line);
+    let command_end = line.iter().position(|ch| ch.is_ascii_whitespace()).unwrap_or(line.len());
+    trim_whitespace(&line[command_end..])
+}
+
+pub(super) fn split_whitespace(line: &[u8]) -> impl Iterator<Item = &[u8]>
+{
+    line.split(|ch| ch.is_ascii_whitespace()).filter(|segment| !segment.is_empty())
+}
+
+// parses the values after the command, missing or invalid values are replaced with 0
+fn read_values_repaired(line: &[u8], values: &mut [f32])
 {
-    match lexical::parse::<T, _>(bytes)
+    for (value, segment) in values.iter_mut().zip(split_whitespace(line).skip(1))
     {
-        Ok(val) => Ok(val),
-        Err(_) => Err(format_parse_error::<T>(bytes).into())
+        *value = fast_float::parse::<f32, _>(segment).unwrap_or(0.0);
     }
 }
 
-fn read_vertex<'a, Iter>(params_iter: &'a mut Iter) -> Result<(f32, f32, f32), Box<dyn std::error::Error>>
+fn read_vertex<'a, Iter>(source_line: SourceLine, cmd: &[u8], params_iter: &mut Iter) -> Result<(f32, f32, f32), ObjError>
 where
     Iter: Iterator<Item = &'a [u8]>
 {
@@ -45,7 +65,7 @@ where
 
     for segment in params_iter
     {
-        vertex[count] = try_parse_f32(segment)?;
+        vertex[count] = try_parse_f32(source_line, segment)?;
         count += 1;
         if count == 3
         {
@@ -55,7 +75,7 @@ where
 
     if count < 3
     {
-        Err(format!("3 vertex coordinates are required, only {} found", count).into())
+        Err(ObjError::TooFewValues { location: source_line.location(cmd), expected: 3, found: count })
     }
     else
     {
@@ -63,7 +83,91 @@ where
     }
 }
 
-fn read_vertex_texcoord<'a, Iter>(params_iter: &'a mut Iter) -> Result<(f32, f32), Box<dyn std::error::Error>>
+// the values after the vertex position, the meaning depends on the number of values:
+// v x y z w is a weight, v x y z r g b is a color, and v x y z r g b a is a color with alpha (which is ignored)
+// the values end at the first token which is not a number, e.g. a trailing comment
+enum ObjVertexExtra
+{
+    None,
+    Weight(f32),
+    Color(f32, f32, f32)
+}
+
+fn read_vertex_extra<'a, Iter>(source_line: SourceLine, params_iter: &mut Iter) -> Result<ObjVertexExtra, ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    let mut values = [0f32; 4];
+    let mut count = 0;
+    let mut first_segment: &[u8] = &[];
+
+    for segment in params_iter
+    {
+        if count == 0
+        {
+            first_segment = segment;
+        }
+
+        values[count] = match try_parse_f32(source_line, segment)
+        {
+            Ok(value) => value,
+            Err(_) => break
+        };
+
+        count += 1;
+        if count == values.len()
+        {
+            break;
+        }
+    }
+
+    match count
+    {
+        1 => Ok(ObjVertexExtra::Weight(values[0])),
+        2 => Err(ObjError::TooFewValues { location: source_line.location(first_segment), expected: 3, found: 2 }),
+        3 | 4 => Ok(ObjVertexExtra::Color(values[0], values[1], values[2])),
+        _ => Ok(ObjVertexExtra::None)
+    }
+}
+
+fn parse_hex_byte(bytes: &[u8]) -> Option<u8>
+{
+    let digit = |ch: u8| (ch as char).to_digit(16);
+    Some((digit(bytes[0])? * 16 + digit(bytes[1])?) as u8)
+}
+
+// zbrush polypaint, each value is MMRRGGBB in hex (mask, red, green, blue), for the vertices in order
+fn read_mrgb_values<'a, Iter>(source_line: SourceLine, params_iter: &mut Iter, values: &mut Vec<(f32, Vec3)>) -> Result<(), ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    for segment in params_iter
+    {
+        for chunk in segment.chunks(8)
+        {
+            let mut bytes = [0f32; 4];
+            for (idx, value) in bytes.iter_mut().enumerate()
+            {
+                let byte = chunk.get(idx * 2..idx * 2 + 2).and_then(parse_hex_byte);
+                *value = byte.ok_or_else(|| ObjError::ParseInt(source_line.location(chunk)))? as f32 / 255.0;
+            }
+
+            values.push((bytes[0], Vec3::new(bytes[1], bytes[2], bytes[3])));
+        }
+    }
+
+    Ok(())
+}
+
+// a token which is not a number is not a value, e.g. a trailing comment
+fn read_optional_value<'a, Iter>(source_line: SourceLine, params_iter: &mut Iter) -> Option<f32>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    params_iter.next().and_then(|segment| try_parse_f32(source_line, segment).ok())
+}
+
+fn read_vertex_texcoord<'a, Iter>(source_line: SourceLine, cmd: &[u8], params_iter: &mut Iter) -> Result<(f32, f32), ObjError>
 where
     Iter: Iterator<Item = &'a [u8]>
 {
@@ -72,7 +176,7 @@ where
 
     for segment in params_iter
     {
-        vertex[count] = try_parse_f32(segment)?;
+        vertex[count] = try_parse_f32(source_line, segment)?;
         count += 1;
         if count == 2
         {
@@ -82,7 +186,7 @@ where
 
     if count < 2
     {
-        Err(format!("2 coordinates are required, only {} found", count).into())
+        Err(ObjError::TooFewValues { location: source_line.location(cmd), expected: 2, found: count })
     }
     else
     {
@@ -90,11 +194,37 @@ where
     }
 }
 
-struct ObjVertexRelative
+// s off and s 0 both turn smoothing off
+fn read_smoothing_group<'a, Iter>(source_line: SourceLine, cmd: &[u8], params_iter: &mut Iter) -> Result<u32, ObjError>
+where
+    Iter: Iterator<Item = &'a [u8]>
+{
+    match params_iter.next()
+    {
+        Some(b"off") => Ok(0),
+        Some(segment) =>
+        {
+            let smoothing_group = try_parse_i32(source_line, segment)?;
+            if smoothing_group < 0
+            {
+                Err(ObjError::ParseInt(source_line.location(segment)))
+            }
+            else
+            {
+                Ok(smoothing_group as u32)
+            }
+        },
+        None => Err(ObjError::MissingArgument(source_line.location(cmd)))
+    }
+}
+
+pub(super) struct ObjVertexRelative
 {
-    position_index: i32,
+    pub(super) position_index: i32,
     texcoord_index: Option<i32>,
-    normal_index: Option<i32>
+    normal_index: Option<i32>,
+    // byte range of the vertex in the line, used for error reporting
+    segment: std::ops::Range<usize>
 }
 
 #[derive(Copy, Clone)]
@@ -110,9 +240,8 @@ impl std::hash::Hash for ObjVertexAbsolute
     fn hash<H: std::hash::Hasher>(&self, state: &mut H)
     {
         state.write_u32(self.position_index);
-        state.write_u32(if let Some(val) = self.texcoord_index { val } else { 0 });
-        state.write_u32(if let Some(val) = self.normal_index { val } else { 0 });
-        state.finish();
+        state.write_u32(self.texcoord_index.unwrap_or_default());
+        state.write_u32(self.normal_index.unwrap_or_default());
     }
 }
 
@@ -133,7 +262,9 @@ const FACE_TYPE_INDEX_AND_TEXCOORD: u8 = 0b011;
 const FACE_TYPE_INDEX_AND_NORMAL: u8 = 0b101;
 const FACE_TYPE_INDEX_AND_TEXCOORD_AND_NORMAL: u8 = 0b111;
 
-fn read_face<'a, Iter>(params_iter: &'a mut Iter, temp_face_data: &mut Vec<ObjVertexRelative>) -> Result<u8, Box<dyn std::error::Error>>
+// also used for lines and points, which have the same vertex format
+pub(super) fn read_face<'a, Iter>(source_line: SourceLine, cmd: &[u8], params_iter: &mut Iter, temp_face_data: &mut Vec<ObjVertexRelative>,
+    min_vertex_count: usize) -> Result<u8, ObjError>
 where
     Iter: Iterator<Item = &'a [u8]>
 {
@@ -152,13 +283,12 @@ where
         // f 1/1/1 2/2/2 3/3/3
 
         let mut current_indices = [None; 3];
-        let mut idx = 0;
         let mut current_face_type = 0_u8;
-        for number_str in segment.split(|ch| *ch == b'/')
+        for (idx, number_str) in segment.split(|ch| *ch == b'/').enumerate()
         {
             if idx >= 3
             {
-                return Err("More than 3 face indices found".into());
+                return Err(ObjError::InvalidFaceVertex(source_line.location(segment)));
             }
 
             current_indices[idx] = if number_str.is_empty()
@@ -168,17 +298,17 @@ where
             else
             {
                 current_face_type |= 1 << idx;
-                Some(try_parse::<i32>(number_str)?)
+                Some(try_parse_i32(source_line, number_str)?)
             };
-
-            idx += 1;
         }
 
+        let segment_start = segment.as_ptr() as usize - source_line.bytes.as_ptr() as usize;
         temp_face_data.push(ObjVertexRelative
         {
-            position_index: if let Some(pos) = current_indices[0] { pos } else { return Err("Position index is required".into()); },
+            position_index: if let Some(pos) = current_indices[0] { pos } else { return Err(ObjError::InvalidFaceVertex(source_line.location(segment))); },
             texcoord_index: current_indices[1],
-            normal_index: current_indices[2]
+            normal_index: current_indices[2],
+            segment: segment_start..segment_start + segment.len()
         });
 
         if let Some(face_type) = line_face_type
@@ -192,12 +322,12 @@ where
                 {
                     if current_face_type != face_type
                     {
-                        return Err("Inconsistent face type".into());
+                        return Err(ObjError::InconsistentFaceType(source_line.location(segment)));
                     }
                 },
                 _ =>
                 {
-                    return Err("Unrecognized face type".into());
+                    return Err(ObjError::InvalidFaceVertex(source_line.location(segment)));
                 }
             };
         }
@@ -207,7 +337,66 @@ where
         }
     }
 
-    line_face_type.ok_or_else(|| "Unknown face type".into())
+    match line_face_type
+    {
+        Some(face_type) if temp_face_data.len() >= min_vertex_count => Ok(face_type),
+        _ => Err(ObjError::TooFewFaceVertices { location: source_line.location(cmd), expected: min_vertex_count, found: temp_face_data.len() })
+    }
+}
+
+// if the index is negative, then it refers to relative vertices (-1 refers to the currently last vertex in the list, -2 to the second last, etc.)
+// the upper bound is only checked if the referenced elements are loaded, count is None otherwise
+pub(super) fn map_index(source_line: SourceLine, vertex: &ObjVertexRelative, index: i32, index_type: ObjIndexType, count: Option<usize>) -> Result<u32, ObjError>
+{
+    let current_count = count.unwrap_or(0);
+    let absolute_index = if index <= 0 { current_count as i64 + index as i64 } else { index as i64 - 1 };
+    if absolute_index < 0 || (count.is_some() && absolute_index as usize >= current_count)
+    {
+        Err(ObjError::IndexOutOfBounds
+        {
+            location: source_line.location(&source_line.bytes[vertex.segment.clone()]),
+            index_type,
+            index,
+            count: current_count
+        })
+    }
+    else
+    {
+        Ok(absolute_index as u32)
+    }
+}
+
+pub(super) fn resolve_vertices(source_line: SourceLine, vertices_relative: &[ObjVertexRelative], vertices_absolute: &mut Vec<ObjVertexAbsolute>,
+    position_count: usize, texcoord_count: Option<usize>, normal_count: Option<usize>) -> Result<(), ObjError>
+{
+    vertices_absolute.clear();
+    for vertex in vertices_relative.iter()
+    {
+        let vertex_absolute = ObjVertexAbsolute
+        {
+            position_index: map_index(source_line, vertex, vertex.position_index, ObjIndexType::Position, Some(position_count))?,
+            texcoord_index: if let Some(idx) = vertex.texcoord_index
+            {
+                Some(map_index(source_line, vertex, idx, ObjIndexType::Texcoord, texcoord_count)?)
+            }
+            else
+            {
+                None
+            },
+            normal_index: if let Some(idx) = vertex.normal_index
+            {
+                Some(map_index(source_line, vertex, idx, ObjIndexType::Normal, normal_count)?)
+            }
+            else
+            {
+                None
+            }
+        };
+
+        vertices_absolute.push(vertex_absolute);
+    }
+
+    Ok(())
 }
 
 bitflags!
@@ -217,17 +406,42 @@ bitflags!
         const NONE = 0x00;
         const LOAD_VERTEX_NORMALS = 0x0001;
         const LOAD_VERTEX_TEXCOORDS = 0x0002;
+        // v x y z r g b, and zbrush polypaint from #MRGB comments
+        const LOAD_VERTEX_COLORS = 0x0004;
+        // v x y z w
+        const LOAD_VERTEX_WEIGHTS = 0x0008;
 
         const LOAD_OBJECTS = 0x0100;
         const LOAD_GROUPS = 0x0200;
         const LOAD_MATERIALS = 0x0400;
+        // l v1/vt1 v2/vt2 ...
+        const LOAD_LINES = 0x0800;
+        // p v1 v2 ...
+        const LOAD_POINTS = 0x1000;
+        // s 1, s off
+        const LOAD_SMOOTHING_GROUPS = 0x2000;
+        // vp, cstype, deg, curv, curv2, surf, parm, trim, hole, end
+        const LOAD_FREE_FORM = 0x4000;
+
+        // invalid statements are skipped or repaired instead of failing, see ObjParseResult::warnings
+        const RECOVER_FROM_ERRORS = 0x10000;
+        // concave polygons are triangulated correctly, instead of using a triangle fan
+        const EAR_CLIPPING_TRIANGULATION = 0x20000;
+        // the faces are stored in ObjObject::polygons too, not just as triangles
+        const KEEP_POLYGONS = 0x40000;
 
         const LOAD_ALL =
             Self::LOAD_VERTEX_NORMALS.bits |
             Self::LOAD_VERTEX_TEXCOORDS.bits |
+            Self::LOAD_VERTEX_COLORS.bits |
+            Self::LOAD_VERTEX_WEIGHTS.bits |
             Self::LOAD_OBJECTS.bits |
             Self::LOAD_GROUPS.bits |
-            Self::LOAD_MATERIALS.bits;
+            Self::LOAD_MATERIALS.bits |
+            Self::LOAD_LINES.bits |
+            Self::LOAD_POINTS.bits |
+            Self::LOAD_SMOOTHING_GROUPS.bits |
+            Self::LOAD_FREE_FORM.bits;
 
     }
 }
@@ -236,17 +450,41 @@ pub struct ObjParseResult
 {
     pub objects: Vec<ObjObject>,
     pub vertex_buffer: Vec<Vec3>,
+    // the weights of v x y z w, same length as vertex_buffer, vertices without a weight have 1
+    pub weight_buffer: Option<Vec<f32>>,
     pub texcoord_buffer: Option<Vec<Vec2>>,
-    pub normal_buffer: Option<Vec<Vec3>>
+    // the w components of vt u v w, same length as texcoord_buffer, texcoords without w have 0
+    pub texcoord_w_buffer: Option<Vec<f32>>,
+    pub normal_buffer: Option<Vec<Vec3>>,
+    // same length as vertex_buffer, vertices without a color are white
+    pub color_buffer: Option<Vec<Vec3>>,
+    // polypaint mask from #MRGB comments in 0..1, same length as vertex_buffer, vertices without a mask have 0
+    pub mask_buffer: Option<Vec<f32>>,
+    pub materials: Vec<ObjMaterial>,
+    // curves and surfaces, use tessellate_free_form to convert them to lines and triangles
+    pub free_form: Option<ObjFreeFormGeometry>,
+    pub warnings: Vec<ObjWarning>
 }
 
-pub fn load_obj(file_path: &str, parse_features: ObjParseFeatures) -> Result<ObjParseResult, Box<dyn std::error::Error>>
+pub fn load_obj(file_path: &str, parse_features: ObjParseFeatures) -> Result<ObjParseResult, ObjError>
 {
-    let file_bytes = std::fs::read(file_path)?;
-    load_obj_from_bytes(file_bytes.as_slice(), parse_features)
+    load_obj_with_texture_loader(file_path, parse_features, &mut ObjTextureLoader::new())
+}
+
+// the texture loader can be used for multiple files, so the same textures are only loaded once
+// the obj file, the material libraries and the textures are loaded with the resolver of the texture loader
+pub fn load_obj_with_texture_loader(file_path: &str, parse_features: ObjParseFeatures, texture_loader: &mut ObjTextureLoader)
+    -> Result<ObjParseResult, ObjError>
+{
+    let resolver = texture_loader.resolver().clone();
+    let path = resolver.resolve_root(file_path).map_err(|error| ObjError::io(Path::new(file_path), error))?;
+    let file_bytes = resolver.read(&path).map_err(|error| ObjError::io(&path, error))?;
+    let base_directory = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_obj(file_bytes.as_slice(), base_directory, parse_features, texture_loader)
 }
 
 #[repr(C)]
+#[derive(Copy, Clone)]
 pub struct Vector2<T>
 {
     pub x: T,
@@ -254,6 +492,7 @@ pub struct Vector2<T>
 }
 
 #[repr(C)]
+#[derive(Copy, Clone)]
 pub struct Vector3<T>
 {
     pub x: T,
@@ -261,9 +500,19 @@ pub struct Vector3<T>
     pub z: T
 }
 
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct Vector4<T>
+{
+    pub x: T,
+    pub y: T,
+    pub z: T,
+    pub w: T
+}
+
 impl<T> Vector2<T>
 {
-    fn new(x: T, y: T) -> Self
+    pub(super) fn new(x: T, y: T) -> Self
     {
         Self { x, y }
     }
@@ -271,202 +520,612 @@ impl<T> Vector2<T>
 
 impl<T> Vector3<T>
 {
-    fn new(x: T, y: T, z: T) -> Self
+    pub(super) fn new(x: T, y: T, z: T) -> Self
     {
         Self { x, y, z }
     }
 }
 
-type Vec2 = Vector2<f32>;
-type Vec3 = Vector3<f32>;
+impl<T> Vector4<T>
+{
+    pub(super) fn new(x: T, y: T, z: T, w: T) -> Self
+    {
+        Self { x, y, z, w }
+    }
+}
+
+pub(super) type Vec2 = Vector2<f32>;
+pub(super) type Vec3 = Vector3<f32>;
+pub(super) type Vec4 = Vector4<f32>;
+
+impl std::ops::Add for Vec3
+{
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3
+    {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl std::ops::Sub for Vec3
+{
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3
+    {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl std::ops::Mul<f32> for Vec3
+{
+    type Output = Vec3;
+
+    fn mul(self, scale: f32) -> Vec3
+    {
+        Vec3::new(self.x * scale, self.y * scale, self.z * scale)
+    }
+}
+
+impl Vec3
+{
+    pub fn dot(self, other: Vec3) -> f32
+    {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Vec3) -> Vec3
+    {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x)
+    }
+
+    pub fn length(self) -> f32
+    {
+        self.dot(self).sqrt()
+    }
+
+    // returns None for zero length vectors
+    pub fn normalized(self) -> Option<Vec3>
+    {
+        let length = self.length();
+        if length > 0.0 && length.is_finite() { Some(self * (1.0 / length)) } else { None }
+    }
+}
+
+// the vectors of the tests are compared with a tolerance for rounding errors
+#[cfg(test)]
+pub(super) fn assert_vec3_eq(actual: Vec3, expected: Vec3)
+{
+    assert!((actual - expected).length() < 1e-5, "({}, {}, {}) != ({}, {}, {})", actual.x, actual.y, actual.z, expected.x, expected.y, expected.z);
+}
+
+// a range of triangles in ObjObject::indices which use the same material
+#[derive(Copy, Clone)]
+pub struct ObjSubmesh
+{
+    // index into ObjParseResult::materials, None if no material is used
+    pub material_index: Option<u32>,
+    pub triangle_start: u32,
+    pub triangle_count: u32
+}
+
+// a named set of triangles in ObjObject::indices, a triangle can be part of multiple groups
+pub struct ObjGroup
+{
+    pub name: Vec<u8>,
+    pub triangle_ranges: Vec<std::ops::Range<u32>>
+}
+
+impl ObjGroup
+{
+    fn add_triangle(&mut self, triangle_index: u32)
+    {
+        match self.triangle_ranges.last_mut()
+        {
+            Some(range) if range.end == triangle_index =>
+            {
+                range.end += 1;
+            },
+            _ =>
+            {
+                self.triangle_ranges.push(triangle_index..triangle_index + 1);
+            }
+        };
+    }
+
+    pub fn triangle_count(&self) -> u32
+    {
+        self.triangle_ranges.iter().map(|range| range.end - range.start).sum()
+    }
+}
+
+// a face as it was in the file, only stored if ObjParseFeatures::KEEP_POLYGONS is set
+pub struct ObjPolygon
+{
+    // range in ObjObject::polygon_vertices
+    pub vertex_start: u32,
+    pub vertex_count: u32,
+    // the first triangle of the polygon in ObjObject::indices, the polygon is split into vertex_count - 2 triangles
+    pub triangle_start: u32
+}
+
+// a line strip, the vertices can have texcoords
+pub struct ObjLine
+{
+    // range in ObjObject::line_vertices
+    pub vertex_start: u32,
+    pub vertex_count: u32,
+    // index into ObjParseResult::materials, None if no material is used
+    pub material_index: Option<u32>
+}
 
 pub struct ObjObject
 {
     pub name: Vec<u8>,
-    pub indices: Vec<Vector3<ObjVertexAbsolute>>
+    // the triangulated faces
+    pub indices: Vec<Vector3<ObjVertexAbsolute>>,
+    pub submeshes: Vec<ObjSubmesh>,
+    pub groups: Vec<ObjGroup>,
+    pub polygons: Vec<ObjPolygon>,
+    pub polygon_vertices: Vec<ObjVertexAbsolute>,
+    // the smoothing group of each triangle in indices, 0 means smoothing is off
+    // empty if there was no s statement before the faces of the object
+    pub smoothing_groups: Vec<u32>,
+    pub lines: Vec<ObjLine>,
+    pub line_vertices: Vec<ObjVertexAbsolute>,
+    // the vertices of all p statements, in order
+    pub points: Vec<ObjVertexAbsolute>
+}
+
+impl ObjObject
+{
+    fn new(name: Vec<u8>) -> Self
+    {
+        Self
+        {
+            name,
+            indices: vec![],
+            submeshes: vec![],
+            groups: vec![],
+            polygons: vec![],
+            polygon_vertices: vec![],
+            smoothing_groups: vec![],
+            lines: vec![],
+            line_vertices: vec![],
+            points: vec![]
+        }
+    }
+
+    // true if the object has no faces, lines or points
+    fn is_empty(&self) -> bool
+    {
+        self.indices.is_empty() && self.lines.is_empty() && self.points.is_empty()
+    }
+
+    pub fn line_vertices(&self, line: &ObjLine) -> &[ObjVertexAbsolute]
+    {
+        let start = line.vertex_start as usize;
+        &self.line_vertices[start..start + line.vertex_count as usize]
+    }
+
+    pub(super) fn push_line(&mut self, vertices: &[ObjVertexAbsolute], material_index: Option<u32>)
+    {
+        self.lines.push(ObjLine
+        {
+            vertex_start: self.line_vertices.len() as u32,
+            vertex_count: vertices.len() as u32,
+            material_index
+        });
+
+        self.line_vertices.extend_from_slice(vertices);
+    }
+
+    pub fn polygon_vertices(&self, polygon: &ObjPolygon) -> &[ObjVertexAbsolute]
+    {
+        let start = polygon.vertex_start as usize;
+        &self.polygon_vertices[start..start + polygon.vertex_count as usize]
+    }
+
+    fn push_polygon(&mut self, vertices: &[ObjVertexAbsolute])
+    {
+        self.polygons.push(ObjPolygon
+        {
+            vertex_start: self.polygon_vertices.len() as u32,
+            vertex_count: vertices.len() as u32,
+            triangle_start: self.indices.len() as u32
+        });
+
+        self.polygon_vertices.extend_from_slice(vertices);
+    }
+
+    pub fn find_group(&self, name: &[u8]) -> Option<&ObjGroup>
+    {
+        self.groups.iter().find(|group| group.name == name)
+    }
+
+    pub fn group_triangles<'a>(&'a self, group: &'a ObjGroup) -> impl Iterator<Item = &'a Vector3<ObjVertexAbsolute>> + 'a
+    {
+        group.triangle_ranges.iter()
+            .flat_map(move |range| self.indices[range.start as usize..range.end as usize].iter())
+    }
+
+    fn get_or_add_group(&mut self, name: &[u8]) -> u32
+    {
+        if let Some(idx) = self.groups.iter().position(|group| group.name == name)
+        {
+            idx as u32
+        }
+        else
+        {
+            self.groups.push(ObjGroup
+            {
+                name: name.to_owned(),
+                triangle_ranges: vec![]
+            });
+
+            self.groups.len() as u32 - 1
+        }
+    }
+
+    pub fn submesh_indices(&self, submesh: &ObjSubmesh) -> &[Vector3<ObjVertexAbsolute>]
+    {
+        let start = submesh.triangle_start as usize;
+        &self.indices[start..start + submesh.triangle_count as usize]
+    }
+
+    // the smoothing group is None if there was no s statement yet
+    pub(super) fn push_triangle(&mut self, triangle: Vector3<ObjVertexAbsolute>, material_index: Option<u32>, group_indices: &[u32],
+        smoothing_group: Option<u32>)
+    {
+        let triangle_index = self.indices.len() as u32;
+        for group_index in group_indices.iter()
+        {
+            self.groups[*group_index as usize].add_triangle(triangle_index);
+        }
+
+        match self.submeshes.last_mut()
+        {
+            Some(submesh) if submesh.material_index == material_index =>
+            {
+                submesh.triangle_count += 1;
+            },
+            _ =>
+            {
+                self.submeshes.push(ObjSubmesh
+                {
+                    material_index,
+                    triangle_start: triangle_index,
+                    triangle_count: 1
+                });
+            }
+        };
+
+        if smoothing_group.is_some() || !self.smoothing_groups.is_empty()
+        {
+            // the previous triangles weren't in a smoothing group
+            self.smoothing_groups.resize(self.indices.len(), 0);
+            self.smoothing_groups.push(smoothing_group.unwrap_or(0));
+        }
+
+        self.indices.push(triangle);
+    }
+
+    // the smoothing group of a triangle in indices, 0 if smoothing is off
+    pub fn smoothing_group(&self, triangle_index: usize) -> u32
+    {
+        self.smoothing_groups.get(triangle_index).copied().unwrap_or(0)
+    }
+}
+
+pub fn load_obj_from_bytes(file_bytes: &[u8], parse_features: ObjParseFeatures) -> Result<ObjParseResult, ObjError>
+{
+    // material libraries are resolved relative to the current working directory
+    load_obj_from_bytes_with_base_directory(file_bytes, Path::new(""), parse_features)
+}
+
+pub fn load_obj_from_bytes_with_base_directory(file_bytes: &[u8], base_directory: &Path, parse_features: ObjParseFeatures)
+    -> Result<ObjParseResult, ObjError>
+{
+    load_obj_from_bytes_with_texture_loader(file_bytes, base_directory, parse_features, &mut ObjTextureLoader::new())
+}
+
+// the material libraries and the textures are resolved relative to the base directory with the resolver of the texture loader
+pub fn load_obj_from_bytes_with_texture_loader(file_bytes: &[u8], base_directory: &Path, parse_features: ObjParseFeatures, texture_loader: &mut ObjTextureLoader)
+    -> Result<ObjParseResult, ObjError>
+{
+    parse_obj(file_bytes, base_directory, parse_features, texture_loader)
 }
 
-pub fn load_obj_from_bytes(file_bytes: &[u8], parse_features: ObjParseFeatures) -> Result<ObjParseResult, Box<dyn std::error::Error>>
+fn parse_obj(file_bytes: &[u8], base_directory: &Path, parse_features: ObjParseFeatures, texture_loader: &mut ObjTextureLoader)
+    -> Result<ObjParseResult, ObjError>
 {
     let load_vertex_normals = (parse_features & ObjParseFeatures::LOAD_VERTEX_NORMALS) != ObjParseFeatures::NONE;
     let load_vertex_texcoords = (parse_features & ObjParseFeatures::LOAD_VERTEX_TEXCOORDS) != ObjParseFeatures::NONE;
+    let load_vertex_colors = (parse_features & ObjParseFeatures::LOAD_VERTEX_COLORS) != ObjParseFeatures::NONE;
+    let load_vertex_weights = (parse_features & ObjParseFeatures::LOAD_VERTEX_WEIGHTS) != ObjParseFeatures::NONE;
     let load_objects = (parse_features & ObjParseFeatures::LOAD_OBJECTS) != ObjParseFeatures::NONE;
     let load_groups = load_objects && (parse_features & ObjParseFeatures::LOAD_GROUPS) != ObjParseFeatures::NONE;
     let load_materials = (parse_features & ObjParseFeatures::LOAD_MATERIALS) != ObjParseFeatures::NONE;
+    let load_lines = (parse_features & ObjParseFeatures::LOAD_LINES) != ObjParseFeatures::NONE;
+    let load_points = (parse_features & ObjParseFeatures::LOAD_POINTS) != ObjParseFeatures::NONE;
+    let load_smoothing_groups = (parse_features & ObjParseFeatures::LOAD_SMOOTHING_GROUPS) != ObjParseFeatures::NONE;
+    let load_free_form = (parse_features & ObjParseFeatures::LOAD_FREE_FORM) != ObjParseFeatures::NONE;
+    let recover_from_errors = (parse_features & ObjParseFeatures::RECOVER_FROM_ERRORS) != ObjParseFeatures::NONE;
+    let ear_clipping_triangulation = (parse_features & ObjParseFeatures::EAR_CLIPPING_TRIANGULATION) != ObjParseFeatures::NONE;
+    let keep_polygons = (parse_features & ObjParseFeatures::KEEP_POLYGONS) != ObjParseFeatures::NONE;
 
     let mut all_objects = Vec::<ObjObject>::new();
-    all_objects.push(ObjObject
-    {
-        name: vec![],
-        indices: vec![]
-    });
+    all_objects.push(ObjObject::new(vec![]));
 
     let mut current_object = &mut all_objects[0];
+    let mut current_object_index = 0;
 
     let mut vertices = Vec::<Vec3>::with_capacity(128);
     let mut texcoords = Vec::<Vec2>::with_capacity(if load_vertex_texcoords { 128 } else { 0 });
     let mut normals = Vec::<Vec3>::with_capacity(if load_vertex_normals { 128 } else { 0 });
+    let mut weights = Vec::<f32>::new();
+    let mut texcoords_w = Vec::<f32>::new();
+    let mut colors = Vec::<Vec3>::new();
+    let mut masks = Vec::<f32>::new();
+    // the next vertex which gets a color from #MRGB
+    let mut mrgb_vertex_index = 0;
+    let mut temp_mrgb_values = Vec::<(f32, Vec3)>::new();
+
+    let mut materials = Vec::<ObjMaterial>::new();
+    let mut current_material_index = None;
+    let mut current_smoothing_group = None;
+    let mut free_form = FreeFormParser::new();
+
+    // the active group names are kept when switching objects, the group indices are only valid for the current object
+    let mut current_group_names = Vec::<Vec<u8>>::new();
+    let mut current_group_indices = Vec::<u32>::new();
 
     let mut temp_face_vertices = Vec::<ObjVertexRelative>::with_capacity(16);
     let mut temp_face_vertices_absolute = Vec::<ObjVertexAbsolute>::with_capacity(16);
+    let mut temp_face_triangles = Vec::<Vector3<ObjVertexAbsolute>>::with_capacity(16);
 
     let mut file_face_type = None;
 
-    for line in file_bytes.split(|ch| *ch == b'\n' || *ch == b'\r')
+    let mut recovery = ErrorRecovery::new(recover_from_errors);
+
+    let mut lines = read_lines(file_bytes);
+    while let Some(source_line) = lines.next_line()
     {
-        let mut split_iter = line.split(|ch| ch.is_ascii_whitespace()).filter(|segment| !segment.is_empty());
+        let line = source_line.bytes;
+        let mut split_iter = split_whitespace(line);
         if let Some(cmd) = split_iter.next()
         {
             match cmd
             {
                 b"v" =>
                 {
-                    let vertex = read_vertex(&mut split_iter)?;
-                    vertices.push(Vec3::new(vertex.0, vertex.1, vertex.2));
-                },
-                b"vt" =>
-                {
-                    if load_vertex_texcoords
+                    // the vertex is repaired instead of skipped, so the indices of the following vertices don't change
+                    let (vertex, repaired) = match read_vertex(source_line, cmd, &mut split_iter)
                     {
-                        let texcoord = read_vertex_texcoord(&mut split_iter)?;
-                        texcoords.push(Vec2::new(texcoord.0, texcoord.1));
-                    }
-                },
-                b"vn" =>
-                {
-                    if load_vertex_normals
+                        Ok(vertex) => (vertex, false),
+                        Err(error) =>
+                        {
+                            recovery.recover(error, ObjWarningKind::StatementRepaired)?;
+                            let mut values = [0f32; 3];
+                            read_values_repaired(line, &mut values);
+                            ((values[0], values[1], values[2]), true)
+                        }
+                    };
+
+                    vertices.push(Vec3::new(vertex.0, vertex.1, vertex.2));
+
+                    // the weight and color of a repaired vertex are dropped
+                    if (load_vertex_colors || load_vertex_weights) && !repaired
                     {
-                        let normal = read_vertex(&mut split_iter)?;
-                        normals.push(Vec3::new(normal.0, normal.1, normal.2));
+                        match read_vertex_extra(source_line, &mut split_iter)
+                        {
+                            Ok(ObjVertexExtra::Weight(weight)) if load_vertex_weights =>
+                            {
+                                // the previous vertices had the default weight
+                                weights.resize(vertices.len() - 1, 1.0);
+                                weights.push(weight);
+                            },
+                            Ok(ObjVertexExtra::Color(r, g, b)) if load_vertex_colors =>
+                            {
+                                // the previous vertices didn't have a color
+                                colors.resize(vertices.len() - 1, Vec3::new(1.0, 1.0, 1.0));
+                                colors.push(Vec3::new(r, g, b));
+                            },
+                            Ok(_) => { },
+                            Err(error) => recovery.recover(error, ObjWarningKind::StatementRepaired)?
+                        };
                     }
                 },
-                b"f" =>
+                b"vt" if load_vertex_texcoords =>
                 {
-                    // check face type
-                    let current_face_type = read_face(&mut split_iter, &mut temp_face_vertices)?;
-                    match file_face_type
+                    let (texcoord, repaired) = match read_vertex_texcoord(source_line, cmd, &mut split_iter)
                     {
-                        Some(face_type) =>
-                        {
-                            if face_type != current_face_type
-                            {
-                                // return Err("Inconsistent face types found across multiple lines".into());
-                            }
-                        },
-                        None =>
+                        Ok(texcoord) => (texcoord, false),
+                        Err(error) =>
                         {
-                            // first face
-                            file_face_type = Some(current_face_type);
+                            recovery.recover(error, ObjWarningKind::StatementRepaired)?;
+                            let mut values = [0f32; 2];
+                            read_values_repaired(line, &mut values);
+                            ((values[0], values[1]), true)
                         }
                     };
 
-                    // if the index is negative, then it refers to relative vertices (-1 refers to the currently last vertex in the list, -2 to the second last, etc.)
-                    let validate_index = |index: i32, current_count: u32| -> Result<u32, Box<dyn std::error::Error>>
+                    texcoords.push(Vec2::new(texcoord.0, texcoord.1));
+
+                    // the optional w component, which is 0 if not present
+                    if !repaired
                     {
-                        if index <= 0
+                        if let Some(w) = read_optional_value(source_line, &mut split_iter)
                         {
-                            let absolute_index = current_count as i32 + index;
-                            if absolute_index < 0
-                            {
-                                Err(format!("Relative index {} is out of bounds", index).into())
-                            }
-                            else
-                            {
-                                Ok(absolute_index as u32)
-                            }
+                            texcoords_w.resize(texcoords.len() - 1, 0.0);
+                            texcoords_w.push(w);
                         }
-                        else
+                    }
+                },
+                b"vn" if load_vertex_normals =>
+                {
+                    let normal = match read_vertex(source_line, cmd, &mut split_iter)
+                    {
+                        Ok(normal) => normal,
+                        Err(error) =>
                         {
-                            Ok((index - 1) as u32)
+                            recovery.recover(error, ObjWarningKind::StatementRepaired)?;
+                            let mut values = [0f32; 3];
+                            read_values_repaired(line, &mut values);
+                            (values[0], values[1], values[2])
                         }
                     };
 
-                    let map_position_index = |index: i32| -> Result<u32, Box<dyn std::error::Error>>
+                    normals.push(Vec3::new(normal.0, normal.1, normal.2));
+                },
+                b"#MRGB" if load_vertex_colors =>
+                {
+                    temp_mrgb_values.clear();
+                    if let Err(error) = read_mrgb_values(source_line, &mut split_iter, &mut temp_mrgb_values)
                     {
-                        validate_index(index, vertices.len() as u32)
-                    };
+                        recovery.recover(error, ObjWarningKind::StatementSkipped)?;
+                        continue;
+                    }
 
-                    let map_texcoord_index = |index: i32| -> Result<u32, Box<dyn std::error::Error>>
+                    let end = mrgb_vertex_index + temp_mrgb_values.len();
+                    if colors.len() < end
                     {
-                        validate_index(index, texcoords.len() as u32)
-                    };
+                        colors.resize(end, Vec3::new(1.0, 1.0, 1.0));
+                    }
 
-                    let map_normal_index = |index: i32| -> Result<u32, Box<dyn std::error::Error>>
+                    if masks.len() < end
                     {
-                        validate_index(index, normals.len() as u32)
-                    };
+                        masks.resize(end, 0.0);
+                    }
 
-                    temp_face_vertices_absolute.clear();
-                    for vertex in temp_face_vertices.iter()
+                    for (idx, (mask, color)) in temp_mrgb_values.iter().enumerate()
                     {
-                        let vertex_absolute = ObjVertexAbsolute
-                        {
-                            position_index: map_position_index(vertex.position_index)?,
-                            texcoord_index: if let Some(idx) = vertex.texcoord_index { Some(map_texcoord_index(idx)?) } else { None },
-                            normal_index: if let Some(idx) = vertex.normal_index { Some(map_normal_index(idx)?) } else { None },
-                        };
-
-                        if vertex_absolute.position_index as usize >= vertices.len()
-                        {
-                            return Err(format!("Vertex position index {} is referenced, but the largest possible index is {}",
-                                vertex_absolute.position_index, vertices.len() - 1).into());
-                        }
+                        masks[mrgb_vertex_index + idx] = *mask;
+                        colors[mrgb_vertex_index + idx] = *color;
+                    }
 
-                        if load_vertex_texcoords
+                    mrgb_vertex_index = end;
+                },
+                b"f" =>
+                {
+                    let face_result = read_face(source_line, cmd, &mut split_iter, &mut temp_face_vertices, 3).and_then(|current_face_type|
+                    {
+                        // check face type
+                        match file_face_type
                         {
-                            if let Some(texcoord_index) = vertex_absolute.texcoord_index
+                            Some(face_type) =>
                             {
-                                if texcoord_index as usize >= texcoords.len()
+                                if face_type != current_face_type
                                 {
-                                    return Err(format!("Vertex texcoord index {} is referenced, but the largest possible index is {}",
-                                        texcoord_index, texcoords.len() - 1).into());
+                                    // return Err("Inconsistent face types found across multiple lines".into());
                                 }
-                            }
-                        }
-
-                        if load_vertex_normals
-                        {
-                            if let Some(normal_index) = vertex_absolute.normal_index
+                            },
+                            None =>
                             {
-                                if normal_index as usize >= normals.len()
-                                {
-                                    return Err(format!("Vertex normal index {} is referenced, but the largest possible index is {}",
-                                        normal_index, normals.len() - 1).into());
-                                }
+                                // first face
+                                file_face_type = Some(current_face_type);
                             }
-                        }
+                        };
+
+                        resolve_vertices(source_line, &temp_face_vertices, &mut temp_face_vertices_absolute, vertices.len(),
+                            if load_vertex_texcoords { Some(texcoords.len()) } else { None },
+                            if load_vertex_normals { Some(normals.len()) } else { None })?;
 
-                        temp_face_vertices_absolute.push(vertex_absolute);
+                        Ok(())
+                    });
+
+                    if let Err(error) = face_result
+                    {
+                        recovery.recover(error, ObjWarningKind::StatementSkipped)?;
+                        continue;
                     }
 
-                    if temp_face_vertices_absolute.len() < 3
+                    if keep_polygons
                     {
-                        return Err("At least 3 vertex indices are required".into());
+                        current_object.push_polygon(&temp_face_vertices_absolute);
                     }
 
-                    let idx0 = temp_face_vertices_absolute[0];
-                    for i in 2..temp_face_vertices_absolute.len()
+                    temp_face_triangles.clear();
+                    if ear_clipping_triangulation
                     {
-                        let idx1 = temp_face_vertices_absolute[i - 1];
-                        let idx2 = temp_face_vertices_absolute[i];
+                        triangulate_ear_clipping(&temp_face_vertices_absolute, &vertices, &mut temp_face_triangles);
+                    }
+                    else
+                    {
+                        triangulate_fan(&temp_face_vertices_absolute, &mut temp_face_triangles);
+                    }
 
-                        current_object.indices.push(Vector3::new(idx0, idx1, idx2));
+                    for triangle in temp_face_triangles.iter()
+                    {
+                        current_object.push_triangle(*triangle, current_material_index, &current_group_indices, current_smoothing_group);
                     }
                 },
-                b"o" if load_objects =>
+                b"l" if load_lines =>
                 {
-                    let start_index = if let Some(pos) = line[1..].iter().position(|ch| !ch.is_ascii_whitespace())
+                    let line_result = read_face(source_line, cmd, &mut split_iter, &mut temp_face_vertices, 2).and_then(|_|
+                    {
+                        resolve_vertices(source_line, &temp_face_vertices, &mut temp_face_vertices_absolute, vertices.len(),
+                            if load_vertex_texcoords { Some(texcoords.len()) } else { None },
+                            if load_vertex_normals { Some(normals.len()) } else { None })
+                    });
+
+                    if let Err(error) = line_result
                     {
-                        pos + 1
+                        recovery.recover(error, ObjWarningKind::StatementSkipped)?;
+                        continue;
                     }
-                    else
+
+                    current_object.push_line(&temp_face_vertices_absolute, current_material_index);
+                },
+                b"p" if load_points =>
+                {
+                    let point_result = read_face(source_line, cmd, &mut split_iter, &mut temp_face_vertices, 1).and_then(|_|
+                    {
+                        resolve_vertices(source_line, &temp_face_vertices, &mut temp_face_vertices_absolute, vertices.len(),
+                            if load_vertex_texcoords { Some(texcoords.len()) } else { None },
+                            if load_vertex_normals { Some(normals.len()) } else { None })
+                    });
+
+                    if let Err(error) = point_result
+                    {
+                        recovery.recover(error, ObjWarningKind::StatementSkipped)?;
+                        continue;
+                    }
+
+                    current_object.points.extend_from_slice(&temp_face_vertices_absolute);
+                },
+                b"s" if load_smoothing_groups =>
+                {
+                    match read_smoothing_group(source_line, cmd, &mut split_iter)
                     {
-                        1
+                        Ok(smoothing_group) => current_smoothing_group = Some(smoothing_group),
+                        Err(error) => recovery.recover(error, ObjWarningKind::StatementSkipped)?
+                    };
+                },
+                b"vp" | b"cstype" | b"deg" | b"curv" | b"curv2" | b"surf" | b"parm" | b"trim" | b"hole" | b"end" if load_free_form =>
+                {
+                    let counts = ObjVertexCounts
+                    {
+                        position: vertices.len(),
+                        texcoord: if load_vertex_texcoords { Some(texcoords.len()) } else { None },
+                        normal: if load_vertex_normals { Some(normals.len()) } else { None }
                     };
 
-                    let object_name = &line[start_index..];
+                    if let Err(error) = free_form.parse_statement(source_line, cmd, &mut split_iter, &counts,
+                        current_object_index as u32, current_material_index)
+                    {
+                        recovery.recover(error, ObjWarningKind::StatementSkipped)?;
+                    }
+                },
+                b"o" if load_objects =>
+                {
+                    let object_name = get_statement_argument(line);
 
-                    let current_object_index =
+                    current_object_index =
                     {
                         let index = all_objects.iter().enumerate()
                             .find(|(_idx, object)| object.name == object_name)
@@ -479,41 +1138,315 @@ pub fn load_obj_from_bytes(file_bytes: &[u8], parse_features: ObjParseFeatures)
                         else
                         {
                             let idx = all_objects.len();
-                            all_objects.push(ObjObject
-                            {
-                                name: object_name.to_owned(),
-                                indices: vec![]
-                            });
+                            all_objects.push(ObjObject::new(object_name.to_owned()));
 
                             idx
                         }
                     };
 
                     current_object = &mut all_objects[current_object_index];
+
+                    current_group_indices.clear();
+                    for group_name in current_group_names.iter()
+                    {
+                        current_group_indices.push(current_object.get_or_add_group(group_name));
+                    }
                 },
                 b"g" if load_groups =>
                 {
-                    // TODO
+                    // g name1 name2 ...
+                    // if no name is specified, then the group name is "default"
+                    current_group_names.clear();
+                    for group_name in split_iter
+                    {
+                        current_group_names.push(group_name.to_owned());
+                    }
+
+                    if current_group_names.is_empty()
+                    {
+                        current_group_names.push(b"default".to_vec());
+                    }
+
+                    current_group_indices.clear();
+                    for group_name in current_group_names.iter()
+                    {
+                        current_group_indices.push(current_object.get_or_add_group(group_name));
+                    }
                 },
                 b"mtllib" if load_materials =>
                 {
-                    // TODO
+                    // there can be multiple files in a single mtllib statement
+                    for file_name in split_iter
+                    {
+                        let file_name_str = match std::str::from_utf8(file_name)
+                        {
+                            Ok(file_name_str) => file_name_str,
+                            Err(_) =>
+                            {
+                                recovery.recover(ObjError::InvalidPath(source_line.location(file_name)), ObjWarningKind::StatementSkipped)?;
+                                continue;
+                            }
+                        };
+
+                        let mut library_recovery = ErrorRecovery::new(recover_from_errors);
+                        let library_result = load_mtl_from_path(Some(base_directory), file_name_str, texture_loader, &mut library_recovery);
+
+                        // errors and warnings in the library are reported at the mtllib statement
+                        let wrap_error = |error| ObjError::MaterialLibrary { location: source_line.location(file_name), error: Box::new(error) };
+                        for warning in library_recovery.warnings
+                        {
+                            recovery.warnings.push(ObjWarning { kind: warning.kind, error: wrap_error(warning.error) });
+                        }
+
+                        match library_result
+                        {
+                            Ok(mut library) => materials.append(&mut library),
+                            // a missing library is only a warning, the geometry is still usable
+                            Err(error @ ObjError::Io { .. }) =>
+                            {
+                                recovery.warnings.push(ObjWarning { kind: ObjWarningKind::StatementSkipped, error: wrap_error(error) });
+                            },
+                            Err(error) => recovery.recover(wrap_error(error), ObjWarningKind::StatementSkipped)?
+                        };
+                    }
+                },
+                b"usemtl" if load_materials =>
+                {
+                    let material_name = String::from_utf8_lossy(get_statement_argument(line));
+                    let index = if let Some(idx) = materials.iter().position(|material| material.name == material_name)
+                    {
+                        idx
+                    }
+                    else
+                    {
+                        // the material is not defined in any of the libraries, use the default values
+                        materials.push(ObjMaterial::new(material_name.into_owned()));
+                        materials.len() - 1
+                    };
+
+                    current_material_index = Some(index as u32);
                 },
                 _ => { }
             };
         }
     }
 
-    if all_objects[0].indices.is_empty()
+    if all_objects[0].is_empty() && !free_form.references_object(0)
     {
         // remove default object if empty
         all_objects.remove(0);
+        free_form.shift_object_indices();
+    }
+
+    for object in all_objects.iter_mut()
+    {
+        // groups which were declared, but never received any faces
+        object.groups.retain(|group| !group.triangle_ranges.is_empty());
+    }
+
+    if !weights.is_empty()
+    {
+        weights.resize(vertices.len(), 1.0);
+    }
+
+    if !texcoords_w.is_empty()
+    {
+        texcoords_w.resize(texcoords.len(), 0.0);
+    }
+
+    if !colors.is_empty()
+    {
+        colors.resize(vertices.len(), Vec3::new(1.0, 1.0, 1.0));
+    }
+
+    if !masks.is_empty()
+    {
+        masks.resize(vertices.len(), 0.0);
     }
 
     Ok(ObjParseResult {
         objects: all_objects,
         vertex_buffer: vertices,
+        weight_buffer: if load_vertex_weights && !weights.is_empty() { Some(weights) } else { None },
         texcoord_buffer: if load_vertex_texcoords && !texcoords.is_empty() { Some(texcoords) } else { None },
-        normal_buffer: if load_vertex_normals && !normals.is_empty() { Some(normals) } else { None }
+        texcoord_w_buffer: if load_vertex_texcoords && !texcoords_w.is_empty() { Some(texcoords_w) } else { None },
+        normal_buffer: if load_vertex_normals && !normals.is_empty() { Some(normals) } else { None },
+        color_buffer: if load_vertex_colors && !colors.is_empty() { Some(colors) } else { None },
+        mask_buffer: if load_vertex_colors && !masks.is_empty() { Some(masks) } else { None },
+        materials,
+        free_form: if load_free_form && !free_form.geometry.is_empty() { Some(free_form.geometry) } else { None },
+        warnings: recovery.warnings
     })
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use super::super::resolver::ObjFileResolver;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    // serves the files from memory, the names are relative to the parent directory
+    struct MemoryResolver
+    {
+        files: HashMap<PathBuf, Vec<u8>>
+    }
+
+    impl ObjFileResolver for MemoryResolver
+    {
+        fn resolve(&self, parent_directory: &Path, name: &str) -> std::io::Result<PathBuf>
+        {
+            let path = parent_directory.join(name);
+            if self.files.contains_key(&path)
+            {
+                Ok(path)
+            }
+            else
+            {
+                Err(std::io::Error::new(std::io::ErrorKind::NotFound, "file not found"))
+            }
+        }
+
+        fn read(&self, path: &Path) -> std::io::Result<Vec<u8>>
+        {
+            self.files.get(path).cloned().ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "file not found"))
+        }
+    }
+
+    fn load(source: &str) -> ObjParseResult
+    {
+        load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL).unwrap()
+    }
+
+    #[test]
+    fn vertex_extra_with_trailing_text()
+    {
+        let result = load("v 0 0 0 # note\nv 1 0 0 0.5 # weight\nv 0 1 0 0.25 junk\nf 1 2 3\n");
+        assert_eq!(result.weight_buffer, Some(vec![1.0, 0.5, 0.25]));
+        assert!(result.warnings.is_empty());
+
+        let result = load("v 0 0 0 1 0 0 #red\nv 1 0 0 0 1 0 1 junk\nv 0 1 0\nf 1 2 3\n");
+        let colors = result.color_buffer.unwrap().iter().map(|color| [color.x, color.y, color.z]).collect::<Vec<[f32; 3]>>();
+        assert_eq!(colors, vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 1.0]]);
+    }
+
+    #[test]
+    fn incomplete_vertex_color()
+    {
+        // the position is kept, the extra values are dropped
+        let source = "v 0 0 0 1 0\nv 1 0 0 0 1 0\nv 0 1 0\nf 1 2 3\n";
+        let result = load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL | ObjParseFeatures::RECOVER_FROM_ERRORS).unwrap();
+        assert_eq!(result.vertex_buffer.len(), 3);
+        let colors = result.color_buffer.unwrap().iter().map(|color| [color.x, color.y, color.z]).collect::<Vec<[f32; 3]>>();
+        assert_eq!(colors, vec![[1.0, 1.0, 1.0], [0.0, 1.0, 0.0], [1.0, 1.0, 1.0]]);
+
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].kind, ObjWarningKind::StatementRepaired);
+        match &result.warnings[0].error
+        {
+            ObjError::TooFewValues { location, expected: 3, found: 2 } => assert_eq!((location.line, location.column), (1, 9)),
+            error => panic!("unexpected error {}", error)
+        }
+
+        let result = load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL);
+        assert!(matches!(result, Err(ObjError::TooFewValues { .. })));
+    }
+
+    #[test]
+    fn texcoord_w()
+    {
+        let result = load("vt 0.5 0.5\nvt 0.5 0.5 0.25\nvt 0.5 0.5 junk\nvt 0.5 0.5 0.75 # note\n");
+        assert_eq!(result.texcoord_w_buffer, Some(vec![0.0, 0.25, 0.0, 0.75]));
+        assert_eq!(result.texcoord_buffer.map(|texcoords| texcoords.len()), Some(4));
+        assert!(result.warnings.is_empty());
+
+        // no w without any vt u v w
+        let result = load("vt 0.5 0.5\nvt 0.5 0.5 junk\n");
+        assert!(result.texcoord_w_buffer.is_none());
+    }
+
+    #[test]
+    fn memory_resolver()
+    {
+        let obj = "mtllib materials/model.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n";
+        let mtl = "newmtl red\nKd 1 0 0\nmap_Kd ../textures/red.png\nnewmtl missing\nmap_Kd missing.png\n";
+        let files = [
+            ("bundle/model.obj", obj.as_bytes()),
+            ("bundle/materials/model.mtl", mtl.as_bytes()),
+            ("bundle/materials/../textures/red.png", b"png")
+        ];
+
+        let resolver = Arc::new(MemoryResolver { files: files.iter().map(|(name, data)| (PathBuf::from(name), data.to_vec())).collect() });
+        let mut texture_loader = ObjTextureLoader::with_resolver(resolver);
+
+        let results = [
+            load_obj_with_texture_loader("bundle/model.obj", ObjParseFeatures::LOAD_ALL, &mut texture_loader).unwrap(),
+            load_obj_from_bytes_with_texture_loader(obj.as_bytes(), Path::new("bundle"), ObjParseFeatures::LOAD_ALL, &mut texture_loader).unwrap()
+        ];
+
+        for result in results.iter()
+        {
+            assert_eq!(result.materials.len(), 2);
+            assert_eq!(result.objects[0].submeshes[0].material_index, Some(0));
+
+            let texture = result.materials[0].diffuse_texture.as_ref().unwrap();
+            assert_eq!(texture.path(), Some(Path::new("bundle/materials/../textures/red.png")));
+            assert_eq!(texture.data().unwrap(), b"png");
+
+            let missing = result.materials[1].diffuse_texture.as_ref().unwrap();
+            assert!(missing.path().is_none() && missing.data().is_err());
+        }
+
+        // both results share the texture file of the loader
+        let textures = results.iter().map(|result| result.materials[0].diffuse_texture.as_ref().unwrap()).collect::<Vec<&ObjTexture>>();
+        assert!(Arc::ptr_eq(&textures[0].file, &textures[1].file));
+
+        let materials = load_mtl_from_bytes_with_texture_loader(mtl.as_bytes(), Path::new("bundle/materials"), &mut texture_loader).unwrap();
+        assert_eq!(materials[0].diffuse_texture.as_ref().unwrap().data().unwrap(), b"png");
+    }
+
+    fn colors(result: &ObjParseResult) -> Vec<[f32; 3]>
+    {
+        result.color_buffer.as_ref().unwrap().iter().map(|color| [color.x, color.y, color.z]).collect()
+    }
+
+    #[test]
+    fn mrgb_values()
+    {
+        // MMRRGGBB, the mask first
+        let result = load("v 0 0 0\nv 1 0 0\n#MRGB ff804020\n");
+        assert_eq!(result.mask_buffer, Some(vec![1.0, 0.0]));
+        assert_eq!(colors(&result), vec![[128.0 / 255.0, 64.0 / 255.0, 32.0 / 255.0], [1.0, 1.0, 1.0]]);
+    }
+
+    #[test]
+    fn mrgb_block_on_several_lines()
+    {
+        // the values of each line continue with the next vertex, a token can contain several values
+        let result = load("v 0 0 0\nv 1 0 0\nv 0 1 0\nv 1 1 0\n#MRGB ffff0000ff00ff00\n#MRGB 000000ff\n#MRGB 80ffffff\n");
+        assert_eq!(result.mask_buffer, Some(vec![1.0, 1.0, 0.0, 128.0 / 255.0]));
+        assert_eq!(colors(&result), vec![[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 1.0, 1.0]]);
+    }
+
+    #[test]
+    fn malformed_mrgb_value()
+    {
+        // the line with the malformed value is skipped, the next line continues at the same vertex
+        let source = "v 0 0 0\nv 1 0 0\n#MRGB ff0000ff ff00zz00\n#MRGB 80ff0000\n";
+        let result = load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL | ObjParseFeatures::RECOVER_FROM_ERRORS).unwrap();
+        assert_eq!(result.mask_buffer, Some(vec![128.0 / 255.0, 0.0]));
+        assert_eq!(colors(&result), vec![[1.0, 0.0, 0.0], [1.0, 1.0, 1.0]]);
+
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.warnings[0].kind, ObjWarningKind::StatementSkipped);
+        match &result.warnings[0].error
+        {
+            ObjError::ParseInt(location) => assert_eq!((location.line, location.column, location.token.as_str()), (3, 16, "ff00zz00")),
+            error => panic!("unexpected error {}", error)
+        }
+
+        assert!(matches!(load_obj_from_bytes(source.as_bytes(), ObjParseFeatures::LOAD_ALL), Err(ObjError::ParseInt(_))));
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::{Path, PathBuf};
+
+// finds and reads the files which are referenced by other files, i.e. obj files, material libraries and textures
+// implement this to load from archives, bundles in memory, caches etc.
+pub trait ObjFileResolver: Send + Sync
+{
+    // the path of the file with the given name, relative to the directory of the file which references it
+    // files with the same path are only loaded once, so the path should be unique for each file
+    fn resolve(&self, parent_directory: &Path, name: &str) -> std::io::Result<PathBuf>;
+
+    // the path of a file which is not referenced by another file, e.g. the file passed to load_obj
+    fn resolve_root(&self, name: &str) -> std::io::Result<PathBuf>
+    {
+        self.resolve(Path::new(""), name)
+    }
+
+    // reads a file with a path from resolve
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>>;
+}
+
+// resolves the names in the file system, the resolved paths are canonical
+#[derive(Default)]
+pub struct ObjFileSystemResolver
+{
+    // searched in this order after the directory of the referencing file
+    pub search_directories: Vec<PathBuf>
+}
+
+impl ObjFileSystemResolver
+{
+    pub fn new() -> Self
+    {
+        Default::default()
+    }
+
+    pub fn with_search_directories(search_directories: Vec<PathBuf>) -> Self
+    {
+        Self { search_directories }
+    }
+}
+
+impl ObjFileResolver for ObjFileSystemResolver
+{
+    // if the name with its directories doesn't exist, only the file name itself is searched
+    // the names are matched case insensitive if there is no exact match, since many files are authored on windows
+    fn resolve(&self, parent_directory: &Path, name: &str) -> std::io::Result<PathBuf>
+    {
+        if Path::new(name).is_absolute()
+        {
+            return self.resolve_root(name);
+        }
+
+        // names from windows use backslashes
+        let components = name.split(['/', '\\']).filter(|component| !component.is_empty()).collect::<Vec<&str>>();
+        let last_component = components.last().copied().unwrap_or("");
+
+        let directories = std::iter::once(parent_directory).chain(self.search_directories.iter().map(|directory| directory.as_path()));
+        let path = directories
+            .filter_map(|directory| find_path(directory, &components).or_else(|| find_path(directory, &[last_component])))
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "file not found"))?;
+
+        std::fs::canonicalize(path)
+    }
+
+    // the name is used as it is, without searching
+    fn resolve_root(&self, name: &str) -> std::io::Result<PathBuf>
+    {
+        if Path::new(name).is_file()
+        {
+            std::fs::canonicalize(name)
+        }
+        else
+        {
+            Err(std::io::Error::new(std::io::ErrorKind::NotFound, "file not found"))
+        }
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>>
+    {
+        std::fs::read(path)
+    }
+}
+
+// joins the components, components which don't exist are matched case insensitive
+fn find_path(directory: &Path, components: &[&str]) -> Option<PathBuf>
+{
+    let mut path = directory.to_path_buf();
+    for component in components.iter()
+    {
+        let exact_path = path.join(component);
+        path = if exact_path.exists()
+        {
+            exact_path
+        }
+        else
+        {
+            let component = component.to_lowercase();
+            // read_dir doesn't accept an empty path for the current directory
+            let directory = if path.as_os_str().is_empty() { Path::new(".") } else { path.as_path() };
+            std::fs::read_dir(directory).ok()?
+                .filter_map(|entry| entry.ok())
+                .find(|entry| entry.file_name().to_string_lossy().to_lowercase() == component)
+                .map(|entry| path.join(entry.file_name()))?
+        };
+    }
+
+    if path.is_file() { Some(path) } else { None }
+}
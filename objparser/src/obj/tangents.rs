@@ -0,0 +1,247 @@
+use super::obj::*;
+use super::mesh::*;
+use std::collections::HashMap;
+
+// the mesh in the form used by mikktspace, the tangents are written per triangle corner
+struct TangentGeometry<'a>
+{
+    indices: &'a [u32],
+    positions: &'a [Vector3<f32>],
+    texcoords: &'a [Vector2<f32>],
+    normals: &'a [Vector3<f32>],
+    corner_tangents: Vec<Vec4>
+}
+
+impl<'a> TangentGeometry<'a>
+{
+    fn vertex(&self, face: usize, vert: usize) -> usize
+    {
+        self.indices[face * 3 + vert] as usize
+    }
+}
+
+impl<'a> bevy_mikktspace::Geometry for TangentGeometry<'a>
+{
+    fn num_faces(&self) -> usize
+    {
+        self.indices.len() / 3
+    }
+
+    fn num_vertices_of_face(&self, _face: usize) -> usize
+    {
+        3
+    }
+
+    fn position(&self, face: usize, vert: usize) -> [f32; 3]
+    {
+        let position = self.positions[self.vertex(face, vert)];
+        [position.x, position.y, position.z]
+    }
+
+    fn normal(&self, face: usize, vert: usize) -> [f32; 3]
+    {
+        let normal = self.normals[self.vertex(face, vert)];
+        [normal.x, normal.y, normal.z]
+    }
+
+    fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2]
+    {
+        let texcoord = self.texcoords[self.vertex(face, vert)];
+        [texcoord.x, texcoord.y]
+    }
+
+    fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize)
+    {
+        self.corner_tangents[face * 3 + vert] = Vec4::new(tangent[0], tangent[1], tangent[2], tangent[3]);
+    }
+}
+
+impl ObjMesh
+{
+    // calculates the tangents with mikktspace, so they match the normal maps baked by most tools
+    // vertices which need different tangents for different triangles are duplicated
+    // returns false if the mesh has no texcoords or normals
+    pub fn generate_tangents(&mut self) -> bool
+    {
+        let (texcoords, normals) = match (&self.texcoords, &self.normals)
+        {
+            (Some(texcoords), Some(normals)) => (texcoords, normals),
+            _ => return false
+        };
+
+        let indices = match &self.indices
+        {
+            ObjIndexBuffer::U16(indices) => indices.iter().map(|idx| *idx as u32).collect::<Vec<u32>>(),
+            ObjIndexBuffer::U32(indices) => indices.clone()
+        };
+
+        let mut geometry = TangentGeometry
+        {
+            indices: &indices,
+            positions: &self.positions,
+            texcoords,
+            normals,
+            corner_tangents: vec![Vec4::new(1.0, 0.0, 0.0, 1.0); indices.len()]
+        };
+
+        if !indices.is_empty() && !bevy_mikktspace::generate_tangents(&mut geometry)
+        {
+            return false;
+        }
+
+        let corner_tangents = geometry.corner_tangents;
+
+        // identical tangents of the same vertex share the same vertex
+        let mut vertex_map = HashMap::<(u32, [u32; 4]), u32>::with_capacity(self.positions.len());
+        let mut source_vertices = Vec::<u32>::with_capacity(self.positions.len());
+        let mut tangents = Vec::<Vec4>::with_capacity(self.positions.len());
+        let mut new_indices = Vec::<u32>::with_capacity(indices.len());
+
+        for (vertex, tangent) in indices.iter().zip(corner_tangents.iter())
+        {
+            let key = (*vertex, [tangent.x.to_bits(), tangent.y.to_bits(), tangent.z.to_bits(), tangent.w.to_bits()]);
+            let index = *vertex_map.entry(key).or_insert_with(||
+            {
+                source_vertices.push(*vertex);
+                tangents.push(*tangent);
+                tangents.len() as u32 - 1
+            });
+
+            new_indices.push(index);
+        }
+
+        if source_vertices.len() != self.positions.len() || source_vertices.iter().enumerate().any(|(idx, vertex)| idx as u32 != *vertex)
+        {
+            fn remap<T: Copy>(values: &[T], source_vertices: &[u32]) -> Vec<T>
+            {
+                source_vertices.iter().map(|vertex| values[*vertex as usize]).collect()
+            }
+
+            self.positions = remap(&self.positions, &source_vertices);
+            self.texcoords = self.texcoords.as_ref().map(|texcoords| remap(texcoords, &source_vertices));
+            self.normals = self.normals.as_ref().map(|normals| remap(normals, &source_vertices));
+            self.colors = self.colors.as_ref().map(|colors| remap(colors, &source_vertices));
+
+            self.indices = match self.indices
+            {
+                ObjIndexBuffer::U16(_) if source_vertices.len() <= 65536 => ObjIndexBuffer::U16(new_indices.iter().map(|idx| *idx as u16).collect()),
+                _ => ObjIndexBuffer::U32(new_indices)
+            };
+        }
+
+        self.tangents = Some(tangents);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn mesh(positions: &[(f32, f32, f32)], texcoords: &[(f32, f32)], normals: &[(f32, f32, f32)], indices: &[u32]) -> ObjMesh
+    {
+        ObjMesh
+        {
+            name: vec![],
+            positions: positions.iter().map(|(x, y, z)| Vec3::new(*x, *y, *z)).collect(),
+            texcoords: Some(texcoords.iter().map(|(u, v)| Vec2::new(*u, *v)).collect()),
+            normals: Some(normals.iter().map(|(x, y, z)| Vec3::new(*x, *y, *z)).collect()),
+            colors: None,
+            tangents: None,
+            indices: ObjIndexBuffer::U32(indices.to_vec()),
+            submeshes: vec![]
+        }
+    }
+
+    fn quad(texcoords: &[(f32, f32)]) -> ObjMesh
+    {
+        mesh(&[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0)], texcoords, &[(0.0, 0.0, 1.0); 4], &[0, 1, 2, 0, 2, 3])
+    }
+
+    // the tangents of the corners of each triangle
+    fn corner_tangents(mesh: &ObjMesh) -> Vec<[f32; 4]>
+    {
+        let tangents = mesh.tangents.as_ref().unwrap();
+        let indices = match &mesh.indices
+        {
+            ObjIndexBuffer::U16(indices) => indices.iter().map(|idx| *idx as u32).collect::<Vec<u32>>(),
+            ObjIndexBuffer::U32(indices) => indices.clone()
+        };
+
+        indices.iter().map(|idx| tangents[*idx as usize]).map(|tangent| [tangent.x, tangent.y, tangent.z, tangent.w]).collect()
+    }
+
+    fn assert_tangents(actual: &[[f32; 4]], expected: &[[f32; 4]])
+    {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected.iter())
+        {
+            assert!(actual.iter().zip(expected.iter()).all(|(a, b)| (a - b).abs() < 1e-5), "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn unit_quad()
+    {
+        // the tangent is the direction of increasing u, the bitangent the direction of increasing v
+        let mut mesh = quad(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        assert!(mesh.generate_tangents());
+        assert_tangents(&corner_tangents(&mesh), &[[1.0, 0.0, 0.0, 1.0]; 6]);
+        assert_eq!(mesh.vertex_count(), 4);
+    }
+
+    #[test]
+    fn mirrored_unit_quad()
+    {
+        // the tangent points to -x, the bitangent (0, 1, 0) = -cross(normal, tangent)
+        let mut mesh = quad(&[(1.0, 0.0), (0.0, 0.0), (0.0, 1.0), (1.0, 1.0)]);
+        assert!(mesh.generate_tangents());
+        assert_tangents(&corner_tangents(&mesh), &[[-1.0, 0.0, 0.0, -1.0]; 6]);
+    }
+
+    #[test]
+    fn hard_edge()
+    {
+        // two faces of a cube with separate normals along the edge at x = 1, the texcoords continue across the edge
+        let mut mesh = mesh(
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0), (1.0, 0.0, 0.0), (1.0, 0.0, -1.0), (1.0, 1.0, -1.0), (1.0, 1.0, 0.0)],
+            &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)],
+            &[(0.0, 0.0, 1.0), (0.0, 0.0, 1.0), (0.0, 0.0, 1.0), (0.0, 0.0, 1.0), (1.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 0.0, 0.0)],
+            &[0, 1, 2, 0, 2, 3, 4, 5, 6, 4, 6, 7]);
+
+        assert!(mesh.generate_tangents());
+        let mut expected = vec![[1.0, 0.0, 0.0, 1.0]; 6];
+        expected.extend_from_slice(&[[0.0, 0.0, -1.0, 1.0]; 6]);
+        assert_tangents(&corner_tangents(&mesh), &expected);
+    }
+
+    #[test]
+    fn separate_triangle_fans()
+    {
+        // two triangles which only share the vertex at the origin (with the same texcoord and normal),
+        // mikktspace doesn't average the tangents of triangles which are not connected by an edge
+        let mut mesh = mesh(
+            &[(0.0, 0.0, 0.0), (1.0, 0.0, 0.0), (1.0, 1.0, 0.0), (0.0, 1.0, 0.0), (-1.0, 1.0, 0.0)],
+            &[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (1.0, 0.0), (1.0, 1.0)],
+            &[(0.0, 0.0, 1.0); 5],
+            &[0, 1, 2, 0, 3, 4]);
+
+        assert!(mesh.generate_tangents());
+        let mut expected = vec![[1.0, 0.0, 0.0, 1.0]; 3];
+        expected.extend_from_slice(&[[0.0, 1.0, 0.0, 1.0]; 3]);
+        assert_tangents(&corner_tangents(&mesh), &expected);
+
+        // the shared vertex is duplicated
+        assert_eq!(mesh.vertex_count(), 6);
+    }
+
+    #[test]
+    fn missing_texcoords()
+    {
+        let mut mesh = quad(&[]);
+        mesh.texcoords = None;
+        assert!(!mesh.generate_tangents());
+        assert!(mesh.tangents.is_none());
+    }
+}
@@ -0,0 +1,196 @@
+use super::obj::*;
+
+pub(super) fn triangulate_fan(polygon: &[ObjVertexAbsolute], triangles: &mut Vec<Vector3<ObjVertexAbsolute>>)
+{
+    let idx0 = polygon[0];
+    for i in 2..polygon.len()
+    {
+        let idx1 = polygon[i - 1];
+        let idx2 = polygon[i];
+
+        triangles.push(Vector3::new(idx0, idx1, idx2));
+    }
+}
+
+// cross product of (b - a) and (c - a) in 2d, positive if a, b, c are in counter-clockwise order
+fn cross_2d(a: Vec2, b: Vec2, c: Vec2) -> f32
+{
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn is_point_in_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool
+{
+    // points on the edges count as inside, so that no triangle touches the rest of the polygon
+    cross_2d(a, b, point) >= 0.0 &&
+    cross_2d(b, c, point) >= 0.0 &&
+    cross_2d(c, a, point) >= 0.0
+}
+
+// projects the polygon onto the plane given by its normal (calculated with Newell's method),
+// and returns the 2d positions in counter-clockwise order relative to the normal
+fn project_polygon(polygon: &[ObjVertexAbsolute], positions: &[Vec3]) -> Option<Vec<Vec2>>
+{
+    let mut normal = Vec3::new(0.0, 0.0, 0.0);
+    for i in 0..polygon.len()
+    {
+        let current = positions[polygon[i].position_index as usize];
+        let next = positions[polygon[(i + 1) % polygon.len()].position_index as usize];
+
+        normal.x += (current.y - next.y) * (current.z + next.z);
+        normal.y += (current.z - next.z) * (current.x + next.x);
+        normal.z += (current.x - next.x) * (current.y + next.y);
+    }
+
+    let normal = normal.normalized()?;
+
+    // any vector which is not parallel to the normal
+    let helper = if normal.x.abs() < 0.9 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+    let axis_u = normal.cross(helper).normalized()?;
+    let axis_v = normal.cross(axis_u);
+
+    Some(polygon.iter().map(|vertex|
+    {
+        let position = positions[vertex.position_index as usize];
+        Vec2::new(position.dot(axis_u), position.dot(axis_v))
+    }).collect())
+}
+
+// triangulates concave polygons by clipping ears, falls back to a triangle fan for degenerate polygons
+pub(super) fn triangulate_ear_clipping(polygon: &[ObjVertexAbsolute], positions: &[Vec3], triangles: &mut Vec<Vector3<ObjVertexAbsolute>>)
+{
+    if polygon.len() <= 3
+    {
+        triangulate_fan(polygon, triangles);
+        return;
+    }
+
+    let projected = if let Some(projected) = project_polygon(polygon, positions)
+    {
+        projected
+    }
+    else
+    {
+        // all vertices are on a line, or in the same position
+        triangulate_fan(polygon, triangles);
+        return;
+    };
+
+    // indices of the vertices which are not clipped yet
+    let mut remaining = (0..polygon.len()).collect::<Vec<usize>>();
+    let mut current = 0;
+    let mut iterations_without_ear = 0;
+
+    while remaining.len() > 3
+    {
+        let count = remaining.len();
+        let prev = remaining[(current + count - 1) % count];
+        let curr = remaining[current];
+        let next = remaining[(current + 1) % count];
+
+        let a = projected[prev];
+        let b = projected[curr];
+        let c = projected[next];
+
+        let is_ear = cross_2d(a, b, c) > 0.0 && !remaining.iter()
+            .filter(|idx| **idx != prev && **idx != curr && **idx != next)
+            .map(|idx| projected[*idx])
+            // vertices in the same position as a corner (e.g. in polygons with holes connected by a bridge edge) don't block the ear
+            .filter(|point| !((point.x == a.x && point.y == a.y) || (point.x == b.x && point.y == b.y) || (point.x == c.x && point.y == c.y)))
+            .any(|point| is_point_in_triangle(point, a, b, c));
+
+        if is_ear
+        {
+            triangles.push(Vector3::new(polygon[prev], polygon[curr], polygon[next]));
+            remaining.remove(current);
+            current %= remaining.len();
+            iterations_without_ear = 0;
+        }
+        else
+        {
+            current = (current + 1) % count;
+            iterations_without_ear += 1;
+
+            if iterations_without_ear > count
+            {
+                // no ear found, the polygon is self-intersecting or degenerate
+                break;
+            }
+        }
+    }
+
+    let rest = remaining.iter().map(|idx| polygon[*idx]).collect::<Vec<ObjVertexAbsolute>>();
+    triangulate_fan(&rest, triangles);
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn triangulate(points: &[(f32, f32)]) -> (Vec<Vec3>, Vec<Vector3<ObjVertexAbsolute>>)
+    {
+        let positions = points.iter().map(|(x, y)| Vec3::new(*x, *y, 0.0)).collect::<Vec<Vec3>>();
+        let polygon = (0..points.len() as u32)
+            .map(|position_index| ObjVertexAbsolute { position_index, texcoord_index: None, normal_index: None })
+            .collect::<Vec<ObjVertexAbsolute>>();
+
+        let mut triangles = vec![];
+        triangulate_ear_clipping(&polygon, &positions, &mut triangles);
+        (positions, triangles)
+    }
+
+    fn signed_area(a: Vec3, b: Vec3, c: Vec3) -> f32
+    {
+        cross_2d(Vec2::new(a.x, a.y), Vec2::new(b.x, b.y), Vec2::new(c.x, c.y)) * 0.5
+    }
+
+    // the triangles have to cover the polygon exactly: all of them counter-clockwise, with the same total area
+    fn assert_covers_polygon(points: &[(f32, f32)], polygon_area: f32)
+    {
+        let (positions, triangles) = triangulate(points);
+        assert_eq!(triangles.len(), points.len() - 2);
+
+        let mut total_area = 0.0;
+        for triangle in triangles.iter()
+        {
+            let area = signed_area(
+                positions[triangle.x.position_index as usize],
+                positions[triangle.y.position_index as usize],
+                positions[triangle.z.position_index as usize]);
+
+            assert!(area > 0.0, "triangle with area {}", area);
+            total_area += area;
+        }
+
+        assert!((total_area - polygon_area).abs() < 1e-4, "{} != {}", total_area, polygon_area);
+    }
+
+    #[test]
+    fn concave_l_shape()
+    {
+        // the fan from the first vertex would have a clockwise triangle at the reflex vertex (1, 1)
+        assert_covers_polygon(&[(2.0, 1.0), (2.0, 2.0), (0.0, 2.0), (0.0, 0.0), (1.0, 0.0), (1.0, 1.0)], 3.0);
+    }
+
+    #[test]
+    fn star()
+    {
+        let points = (0..10).map(|idx|
+        {
+            let angle = idx as f32 * std::f32::consts::PI / 5.0;
+            let radius = if idx % 2 == 0 { 2.0 } else { 1.0 };
+            (radius * angle.cos(), radius * angle.sin())
+        }).collect::<Vec<(f32, f32)>>();
+
+        // 10 triangles between the center and the outer edges
+        let area = 10.0 * 0.5 * 2.0 * 1.0 * (std::f32::consts::PI / 5.0).sin();
+        assert_covers_polygon(&points, area);
+    }
+
+    #[test]
+    fn degenerate_polygon_falls_back_to_fan()
+    {
+        let (_, triangles) = triangulate(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0)]);
+        assert_eq!(triangles.len(), 2);
+    }
+}
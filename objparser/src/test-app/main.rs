@@ -34,7 +34,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>>
             Ok(res) => res,
             Err(err) =>
             {
-                println!("{}", (*err).to_string());
+                println!("{}", err);
                 continue;
             }
         };
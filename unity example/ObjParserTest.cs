@@ -8,6 +8,52 @@ public class ObjParserTest : MonoBehaviour
     public Material defaultMaterial;
     public string importPath;
 
+    /// <summary>
+    /// Same values as ObjParseFeatures in the objparser crate
+    /// </summary>
+    [System.Flags]
+    public enum ObjParseFeatures : uint
+    {
+        None = 0x00,
+        LoadVertexNormals = 0x0001,
+        LoadVertexTexcoords = 0x0002,
+        LoadVertexColors = 0x0004,
+        LoadVertexWeights = 0x0008,
+        LoadObjects = 0x0100,
+        LoadGroups = 0x0200,
+        LoadMaterials = 0x0400,
+        LoadLines = 0x0800,
+        LoadPoints = 0x1000,
+        LoadSmoothingGroups = 0x2000,
+        LoadFreeForm = 0x4000,
+        LoadAll = LoadVertexNormals | LoadVertexTexcoords | LoadVertexColors | LoadVertexWeights | LoadObjects | LoadGroups | LoadMaterials | LoadLines | LoadPoints | LoadSmoothingGroups | LoadFreeForm,
+        RecoverFromErrors = 0x10000,
+        EarClippingTriangulation = 0x20000,
+        KeepPolygons = 0x40000
+    }
+
+    /// <summary>
+    /// Same values as ObjParserErrorKind in the dll crate
+    /// </summary>
+    public enum ObjParserErrorKind : uint
+    {
+        None = 0,
+        Io = 1,
+        ParseFloat = 2,
+        ParseInt = 3,
+        TooFewValues = 4,
+        MissingArgument = 5,
+        InvalidPath = 6,
+        InvalidFaceVertex = 7,
+        InconsistentFaceType = 8,
+        TooFewFaceVertices = 9,
+        IndexOutOfBounds = 10,
+        MaterialLibrary = 11,
+        Panic = 12,
+        InvalidArgument = 13,
+        UnexpectedStatement = 14
+    }
+
     public unsafe static class ExternalDll
     {
         /// <summary>
@@ -15,18 +61,20 @@ public class ObjParserTest : MonoBehaviour
         /// </summary>
         /// <param name="filePathBytes">UTF-8 bytes of the path</param>
         /// <param name="filePathByteCount">The byte count of filePathBytes</param>
+        /// <param name="parseFeatures">Which parts of the file to load</param>
         /// <returns>A pointer to the handle. If null, then an error occured while parsing.</returns>
         [DllImport("objparsers.dll", EntryPoint = "parse_obj_from_file_path", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
-        public static extern void* ParseObjFromFilePath(byte* filePathBytes, uint filePathByteCount);
+        public static extern void* ParseObjFromFilePath(byte* filePathBytes, uint filePathByteCount, ObjParseFeatures parseFeatures);
 
         /// <summary>
         /// Parses an obj file from bytes
         /// </summary>
         /// <param name="fileContentsBytes">Bytes of the file's content</param>
         /// <param name="fileContentsByteCount">The byte count of fileContentsBytes</param>
+        /// <param name="parseFeatures">Which parts of the file to load</param>
         /// <returns>A pointer to the handle. If null, then an error occured while parsing.</returns>
         [DllImport("objparsers.dll", EntryPoint = "parse_obj", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
-        public static extern void* ParseObj(byte* fileContentsBytes, uint fileContentsByteCount);
+        public static extern void* ParseObj(byte* fileContentsBytes, uint fileContentsByteCount, ObjParseFeatures parseFeatures);
 
         /// <summary>
         /// Note: the count is the count of the vertices, which is equal to array size / 3
@@ -40,6 +88,18 @@ public class ObjParserTest : MonoBehaviour
         [DllImport("objparsers.dll", EntryPoint = "get_vertex_positions", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
         public static extern float* GetVertexPositions(void* handle);
 
+        /// <summary>
+        /// 2x 4-byte floats per vertex, null if the file has no texture coordinates
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_vertex_texcoords", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern float* GetVertexTexcoords(void* handle);
+
+        /// <summary>
+        /// 3x 4-byte floats per vertex, null if the file has no normals
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_vertex_normals", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern float* GetVertexNormals(void* handle);
+
         /// <summary>
         /// Index count is equal to the array size (or triangle count * 3)
         /// </summary>
@@ -52,6 +112,72 @@ public class ObjParserTest : MonoBehaviour
         [DllImport("objparsers.dll", EntryPoint = "get_indices", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
         public static extern uint* GetIndices(void* handle);
 
+        /// <summary>
+        /// The functions above return all objects merged into a single mesh, the functions below return a single object
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_object_count", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern uint GetObjectCount(void* handle);
+
+        /// <summary>
+        /// The byte count of the UTF-8 encoded name
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_object_name_length", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern uint GetObjectNameLength(void* handle, uint objectIndex);
+
+        /// <summary>
+        /// UTF-8 bytes of the name, not null terminated
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_object_name", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern byte* GetObjectName(void* handle, uint objectIndex);
+
+        [DllImport("objparsers.dll", EntryPoint = "get_object_vertex_count", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern uint GetObjectVertexCount(void* handle, uint objectIndex);
+
+        [DllImport("objparsers.dll", EntryPoint = "get_object_vertex_positions", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern float* GetObjectVertexPositions(void* handle, uint objectIndex);
+
+        [DllImport("objparsers.dll", EntryPoint = "get_object_vertex_texcoords", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern float* GetObjectVertexTexcoords(void* handle, uint objectIndex);
+
+        [DllImport("objparsers.dll", EntryPoint = "get_object_vertex_normals", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern float* GetObjectVertexNormals(void* handle, uint objectIndex);
+
+        [DllImport("objparsers.dll", EntryPoint = "get_object_index_count", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern uint GetObjectIndexCount(void* handle, uint objectIndex);
+
+        [DllImport("objparsers.dll", EntryPoint = "get_object_indices", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern uint* GetObjectIndices(void* handle, uint objectIndex);
+
+        /// <summary>
+        /// The kind of the error of the last parse call on this thread, None if parsing succeeded
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_last_error_kind", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern ObjParserErrorKind GetLastErrorKind();
+
+        /// <summary>
+        /// 1-based line number of the error, 0 if the error is not related to a line
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_last_error_line", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern uint GetLastErrorLine();
+
+        /// <summary>
+        /// 1-based column of the error, 0 if the error is not related to a line
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_last_error_column", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern uint GetLastErrorColumn();
+
+        /// <summary>
+        /// The byte count of the UTF-8 encoded error message
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_last_error_message_length", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern uint GetLastErrorMessageLength();
+
+        /// <summary>
+        /// UTF-8 bytes of the error message, not null terminated. Valid until the next parse call on the same thread.
+        /// </summary>
+        [DllImport("objparsers.dll", EntryPoint = "get_last_error_message", CallingConvention = CallingConvention.Cdecl, CharSet = CharSet.Auto)]
+        public static extern byte* GetLastErrorMessage();
+
         /// <summary>
         /// Destroys the handle and frees all resources
         /// </summary>
@@ -72,12 +198,19 @@ public class ObjParserTest : MonoBehaviour
             fixed (byte* ptr = pathBytes)
             {
                 sw.Restart();
-                handle = ExternalDll.ParseObj(ptr, (uint)pathBytes.Length);
+                handle = ExternalDll.ParseObjFromFilePath(ptr, (uint)pathBytes.Length, ObjParseFeatures.None);
                 sw.Stop();
                 Debug.Log("parsed in " + sw.ElapsedMilliseconds + "ms");
                 totalMs += sw.ElapsedMilliseconds;
             }
 
+            if (handle == null)
+            {
+                string message = Encoding.UTF8.GetString(ExternalDll.GetLastErrorMessage(), (int)ExternalDll.GetLastErrorMessageLength());
+                Debug.LogError("failed to parse obj (" + ExternalDll.GetLastErrorKind() + "): " + message);
+                return;
+            }
+
             try
             {
                 sw.Restart();